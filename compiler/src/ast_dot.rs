@@ -0,0 +1,396 @@
+use crate::{
+    lexer::Token,
+    parser::{Expr, Function, Literal, Stmt},
+};
+
+/// Renders the program as a Graphviz digraph, with a node per statement and
+/// expression and edges labeled by the role of the child.
+pub fn print_program(statements: &[Stmt]) -> String {
+    let mut graph = Graph::default();
+
+    let program = graph.node("program");
+    for stmt in statements {
+        let child = graph.stmt(stmt);
+        graph.edge(program, child, "statement");
+    }
+
+    format!("digraph ast {{\n{}}}", graph.lines.concat())
+}
+
+#[derive(Default)]
+struct Graph {
+    lines: Vec<String>,
+    next_id: usize,
+}
+
+impl Graph {
+    fn node(&mut self, label: &str) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        self.lines
+            .push(format!("  n{id} [label=\"{}\"];\n", escape(label)));
+
+        id
+    }
+
+    fn edge(&mut self, from: usize, to: usize, label: &str) {
+        self.lines.push(format!(
+            "  n{from} -> n{to} [label=\"{}\"];\n",
+            escape(label)
+        ));
+    }
+
+    /// Adds the statements as children of `parent`, all with the same label.
+    fn stmts(&mut self, parent: usize, statements: &[Stmt], label: &str) {
+        for stmt in statements {
+            let child = self.stmt(stmt);
+            self.edge(parent, child, label);
+        }
+    }
+
+    /// Adds the expression as a child of `parent`.
+    fn child(&mut self, parent: usize, expr: &Expr, label: &str) {
+        let child = self.expr(expr);
+        self.edge(parent, child, label);
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) -> usize {
+        match stmt {
+            Stmt::Expression(expr) => {
+                let node = self.node("expression");
+                self.child(node, expr, "expr");
+                node
+            }
+            Stmt::Print(expr) => {
+                let node = self.node("print");
+                self.child(node, expr, "value");
+                node
+            }
+            Stmt::Assert {
+                condition, message, ..
+            } => {
+                let node = self.node("assert");
+                self.child(node, condition, "condition");
+                if let Some(message) = message {
+                    self.child(node, message, "message");
+                }
+                node
+            }
+            Stmt::Var { name, initializer } => {
+                let node = self.node(&format!("var {}", name.name()));
+                if let Some(initializer) = initializer {
+                    self.child(node, initializer, "initializer");
+                }
+                node
+            }
+            Stmt::Block(statements) => {
+                let node = self.node("block");
+                self.stmts(node, statements, "statement");
+                node
+            }
+            Stmt::If {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                let node = self.node("if");
+                self.child(node, condition, "condition");
+
+                let then_branch = self.stmt(then_branch);
+                self.edge(node, then_branch, "then");
+
+                if let Some(else_branch) = else_branch {
+                    let else_branch = self.stmt(else_branch);
+                    self.edge(node, else_branch, "else");
+                }
+                node
+            }
+            Stmt::While {
+                condition,
+                body,
+                increment,
+            } => {
+                let node = self.node("while");
+                self.child(node, condition, "condition");
+
+                let body = self.stmt(body);
+                self.edge(node, body, "body");
+
+                if let Some(increment) = increment {
+                    self.child(node, increment, "increment");
+                }
+                node
+            }
+            Stmt::DoWhile { body, condition } => {
+                let node = self.node("do while");
+
+                let body = self.stmt(body);
+                self.edge(node, body, "body");
+
+                self.child(node, condition, "condition");
+                node
+            }
+            Stmt::ForIn {
+                name,
+                iterable,
+                body,
+            } => {
+                let node = self.node(&format!("for {} in", name.name()));
+                self.child(node, iterable, "iterable");
+
+                let body = self.stmt(body);
+                self.edge(node, body, "body");
+                node
+            }
+            Stmt::Switch {
+                subject,
+                cases,
+                default,
+            } => {
+                let node = self.node("switch");
+                self.child(node, subject, "subject");
+
+                for (value, body) in cases {
+                    let case = self.node("case");
+                    self.edge(node, case, "case");
+                    self.child(case, value, "value");
+                    self.stmts(case, body, "statement");
+                }
+
+                if let Some(body) = default {
+                    let default = self.node("default");
+                    self.edge(node, default, "default");
+                    self.stmts(default, body, "statement");
+                }
+                node
+            }
+            Stmt::Include { path } => self.node(&format!("include {}", path.lexeme())),
+            Stmt::Break(_) => self.node("break"),
+            Stmt::Continue(_) => self.node("continue"),
+            Stmt::Throw { value, .. } => {
+                let node = self.node("throw");
+                self.child(node, value, "value");
+                node
+            }
+            Stmt::Try {
+                body,
+                name,
+                handler,
+            } => {
+                let node = self.node("try");
+                self.stmts(node, body, "body");
+
+                let catch = self.node(&format!("catch {}", name.name()));
+                self.edge(node, catch, "handler");
+                self.stmts(catch, handler, "statement");
+                node
+            }
+            Stmt::Function(function) => self.function(function),
+            Stmt::Class {
+                name,
+                superclass,
+                methods,
+                static_methods,
+            } => {
+                let node = self.node(&format!("class {}", name.name()));
+                if let Some(superclass) = superclass {
+                    self.child(node, superclass, "superclass");
+                }
+
+                for method in methods {
+                    let method = self.function(method);
+                    self.edge(node, method, "method");
+                }
+
+                for method in static_methods {
+                    let method = self.function(method);
+                    self.edge(node, method, "static method");
+                }
+                node
+            }
+            Stmt::Return { value, .. } => {
+                let node = self.node("return");
+                if let Some(value) = value {
+                    self.child(node, value, "value");
+                }
+                node
+            }
+        }
+    }
+
+    fn function(&mut self, function: &Function) -> usize {
+        let params = function
+            .params
+            .iter()
+            .map(|param| param.name().to_owned())
+            .chain(
+                function
+                    .rest
+                    .iter()
+                    .map(|rest| format!("...{}", rest.name())),
+            )
+            .collect::<Vec<_>>();
+
+        let label = match function.name.name() {
+            name if function.is_getter => format!("get {name}"),
+            "" => format!("fun ({})", params.join(", ")),
+            name => format!("fun {name}({})", params.join(", ")),
+        };
+
+        let node = self.node(&label);
+        for default in &function.defaults {
+            self.child(node, default, "default");
+        }
+        self.stmts(node, &function.body, "statement");
+
+        node
+    }
+
+    fn expr(&mut self, expr: &Expr) -> usize {
+        match expr {
+            Expr::Binary {
+                left,
+                operator,
+                right,
+            }
+            | Expr::Logical {
+                left,
+                operator,
+                right,
+            } => {
+                let node = self.node(lexeme(operator));
+                self.child(node, left, "left");
+                self.child(node, right, "right");
+                node
+            }
+            Expr::Unary { operator, right } => {
+                let node = self.node(lexeme(operator));
+                self.child(node, right, "right");
+                node
+            }
+            Expr::Postfix { left, operator } => {
+                let node = self.node(&format!("postfix {}", lexeme(operator)));
+                self.child(node, left, "left");
+                node
+            }
+            Expr::Literal(literal) => match literal {
+                Literal::Int(integer) => self.node(&integer.to_string()),
+                Literal::Number(number) => self.node(&number.to_string()),
+                Literal::String(string) => self.node(&format!("\"{string}\"")),
+                Literal::Bool(bool) => self.node(&bool.to_string()),
+                Literal::Nil => self.node("nil"),
+            },
+            Expr::Grouping(expr) => {
+                let node = self.node("group");
+                self.child(node, expr, "expr");
+                node
+            }
+            Expr::Variable { name, .. } => self.node(name.name()),
+            Expr::Assign { name, value, .. } => {
+                let node = self.node(&format!("= {}", name.name()));
+                self.child(node, value, "value");
+                node
+            }
+            Expr::Call {
+                callee, arguments, ..
+            } => {
+                let node = self.node("call");
+                self.child(node, callee, "callee");
+                for argument in arguments {
+                    self.child(node, argument, "argument");
+                }
+                node
+            }
+            Expr::Get { object, name } => {
+                let node = self.node(&format!(". {}", name.name()));
+                self.child(node, object, "object");
+                node
+            }
+            Expr::Set {
+                object,
+                name,
+                value,
+                operator,
+            } => {
+                let node = self.node(&format!("{}=. {}", compound_lexeme(operator), name.name()));
+                self.child(node, object, "object");
+                self.child(node, value, "value");
+                node
+            }
+            Expr::Lambda(function) => self.function(function),
+            Expr::Interpolation(parts) => {
+                let node = self.node("str");
+                for part in parts {
+                    self.child(node, part, "part");
+                }
+                node
+            }
+            Expr::Conditional {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                let node = self.node("?:");
+                self.child(node, condition, "condition");
+                self.child(node, then_branch, "then");
+                self.child(node, else_branch, "else");
+                node
+            }
+            Expr::List { elements, .. } => {
+                let node = self.node("list");
+                for element in elements {
+                    self.child(node, element, "element");
+                }
+                node
+            }
+            Expr::Map { entries, .. } => {
+                let node = self.node("map");
+                for (key, value) in entries {
+                    let entry = self.node(":");
+                    self.edge(node, entry, "entry");
+                    self.child(entry, key, "key");
+                    self.child(entry, value, "value");
+                }
+                node
+            }
+            Expr::Index { object, index, .. } => {
+                let node = self.node("[]");
+                self.child(node, object, "object");
+                self.child(node, index, "index");
+                node
+            }
+            Expr::SetIndex {
+                object,
+                index,
+                value,
+                operator,
+                ..
+            } => {
+                let node = self.node(&format!("[]{}=", compound_lexeme(operator)));
+                self.child(node, object, "object");
+                self.child(node, index, "index");
+                self.child(node, value, "value");
+                node
+            }
+            Expr::This { .. } => self.node("this"),
+            Expr::Super { method, .. } => self.node(&format!("super.{}", method.name())),
+        }
+    }
+}
+
+fn lexeme(operator: &Token) -> &'static str {
+    operator.token_type().lexeme().unwrap_or("?")
+}
+
+fn compound_lexeme(operator: &Option<Token>) -> &'static str {
+    operator.as_ref().map_or("", lexeme)
+}
+
+/// Escapes the label for a double quoted DOT string.
+fn escape(label: &str) -> String {
+    label
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
@@ -0,0 +1,250 @@
+use crate::{
+    lexer::Token,
+    parser::{Expr, Function, Literal, Stmt},
+};
+
+pub fn print_stmt(stmt: &Stmt) -> String {
+    match stmt {
+        Stmt::Expression(expr) => print(expr),
+        Stmt::Print(expr) => parenthesize("print", &[expr]),
+        Stmt::Assert {
+            condition, message, ..
+        } => match message {
+            Some(message) => parenthesize("assert", &[condition, message]),
+            None => parenthesize("assert", &[condition]),
+        },
+        Stmt::Var { name, initializer } => match initializer {
+            Some(initializer) => parenthesize(&format!("var {}", name.name()), &[initializer]),
+            None => format!("(var {})", name.name()),
+        },
+        Stmt::Block(statements) => sexpr("block", statements.iter().map(print_stmt)),
+        Stmt::If {
+            condition,
+            then_branch,
+            else_branch,
+        } => sexpr(
+            "if",
+            [print(condition), print_stmt(then_branch)]
+                .into_iter()
+                .chain(else_branch.as_deref().map(print_stmt)),
+        ),
+        Stmt::While {
+            condition,
+            body,
+            increment,
+        } => sexpr(
+            "while",
+            [print(condition), print_stmt(body)]
+                .into_iter()
+                .chain(increment.as_ref().map(print)),
+        ),
+        Stmt::DoWhile { body, condition } => {
+            format!("(do {} {})", print_stmt(body), print(condition))
+        }
+        Stmt::ForIn {
+            name,
+            iterable,
+            body,
+        } => format!(
+            "(for {} {} {})",
+            name.name(),
+            print(iterable),
+            print_stmt(body)
+        ),
+        Stmt::Switch {
+            subject,
+            cases,
+            default,
+        } => {
+            let cases = cases.iter().map(|(value, body)| {
+                sexpr(
+                    &format!("case {}", print(value)),
+                    body.iter().map(print_stmt),
+                )
+            });
+            let default = default
+                .iter()
+                .map(|body| sexpr("default", body.iter().map(print_stmt)));
+
+            sexpr(
+                "switch",
+                std::iter::once(print(subject)).chain(cases).chain(default),
+            )
+        }
+        Stmt::Throw { value, .. } => parenthesize("throw", &[value]),
+        Stmt::Try {
+            body,
+            name,
+            handler,
+        } => sexpr(
+            "try",
+            [
+                sexpr("block", body.iter().map(print_stmt)),
+                sexpr(
+                    &format!("catch {}", name.name()),
+                    handler.iter().map(print_stmt),
+                ),
+            ],
+        ),
+        Stmt::Include { path } => format!("(include {})", path.lexeme()),
+        Stmt::Break(_) => String::from("(break)"),
+        Stmt::Continue(_) => String::from("(continue)"),
+        Stmt::Return { value, .. } => match value {
+            Some(value) => parenthesize("return", &[value]),
+            None => String::from("(return)"),
+        },
+        Stmt::Function(function) => print_function(function),
+        Stmt::Class {
+            name,
+            superclass,
+            methods,
+            static_methods,
+        } => {
+            let name = match superclass {
+                Some(superclass) => format!("class {} < {}", name.name(), print(superclass)),
+                None => format!("class {}", name.name()),
+            };
+
+            let static_methods = static_methods
+                .iter()
+                .map(|method| format!("(static {})", print_function(method)));
+
+            sexpr(
+                &name,
+                methods
+                    .iter()
+                    .map(|method| print_function(method))
+                    .chain(static_methods),
+            )
+        }
+    }
+}
+
+fn print_function(function: &Function) -> String {
+    let (required, optional) = function.params.split_at(function.required_params());
+    let params = required
+        .iter()
+        .map(|param| param.name().to_owned())
+        .chain(
+            optional
+                .iter()
+                .zip(&function.defaults)
+                .map(|(param, default)| parenthesize(&format!("= {}", param.name()), &[default])),
+        )
+        .chain(
+            function
+                .rest
+                .iter()
+                .map(|rest| format!("...{}", rest.name())),
+        )
+        .collect::<Vec<_>>();
+
+    let name = match function.name.name() {
+        name if function.is_getter => format!("get {name}"),
+        "" => format!("fun ({})", params.join(" ")),
+        name => format!("fun {name} ({})", params.join(" ")),
+    };
+
+    sexpr(&name, function.body.iter().map(print_stmt))
+}
+
+/// Renders the expression as a parenthesized s-expression, e.g.
+/// `(* (- 123) (group 45.67))`.
+pub fn print(expr: &Expr) -> String {
+    match expr {
+        Expr::Binary {
+            left,
+            operator,
+            right,
+        }
+        | Expr::Logical {
+            left,
+            operator,
+            right,
+        } => parenthesize(operator_lexeme(operator), &[left, right]),
+        Expr::Unary { operator, right } => parenthesize(operator_lexeme(operator), &[right]),
+        Expr::Postfix { left, operator } => {
+            parenthesize(&format!("postfix {}", operator_lexeme(operator)), &[left])
+        }
+        Expr::Literal(literal) => match literal {
+            Literal::Int(integer) => integer.to_string(),
+            Literal::Number(number) => number.to_string(),
+            Literal::String(string) => string.clone(),
+            Literal::Bool(bool) => bool.to_string(),
+            Literal::Nil => String::from("nil"),
+        },
+        Expr::Grouping(expr) => parenthesize("group", &[expr]),
+        Expr::Variable { name, .. } => name.name().to_owned(),
+        Expr::Call {
+            callee, arguments, ..
+        } => sexpr(
+            "call",
+            std::iter::once(print(callee)).chain(arguments.iter().map(print)),
+        ),
+        Expr::Assign { name, value, .. } => parenthesize(&format!("= {}", name.name()), &[value]),
+        Expr::Get { object, name } => parenthesize(&format!(". {}", name.name()), &[object]),
+        Expr::Set {
+            object,
+            name,
+            value,
+            operator,
+        } => parenthesize(
+            &format!("{}=. {}", compound_lexeme(operator), name.name()),
+            &[object, value],
+        ),
+        Expr::Lambda(function) => print_function(function),
+        Expr::List { elements, .. } => sexpr("list", elements.iter().map(print)),
+        Expr::Map { entries, .. } => sexpr(
+            "map",
+            entries
+                .iter()
+                .map(|(key, value)| parenthesize(":", &[key, value])),
+        ),
+        Expr::Index { object, index, .. } => parenthesize("[]", &[object, index]),
+        Expr::SetIndex {
+            object,
+            index,
+            value,
+            operator,
+            ..
+        } => parenthesize(
+            &format!("[]{}=", compound_lexeme(operator)),
+            &[object, index, value],
+        ),
+        Expr::Interpolation(parts) => sexpr("str", parts.iter().map(print)),
+        Expr::Conditional {
+            condition,
+            then_branch,
+            else_branch,
+        } => parenthesize("?:", &[condition, then_branch, else_branch]),
+        Expr::This { .. } => String::from("this"),
+        Expr::Super { method, .. } => format!("super.{}", method.name()),
+    }
+}
+
+fn parenthesize(name: &str, exprs: &[&Expr]) -> String {
+    sexpr(name, exprs.iter().map(|expr| print(expr)))
+}
+
+fn sexpr(name: &str, parts: impl IntoIterator<Item = String>) -> String {
+    let mut sexpr = format!("({name}");
+
+    for part in parts {
+        sexpr.push(' ');
+        sexpr.push_str(&part);
+    }
+
+    sexpr.push(')');
+
+    sexpr
+}
+
+fn operator_lexeme(operator: &Token) -> &'static str {
+    operator.token_type().lexeme().unwrap_or("?")
+}
+
+/// The operator in front of the `=` of a compound assignment, nothing for a
+/// plain one.
+fn compound_lexeme(operator: &Option<Token>) -> &'static str {
+    operator.as_ref().map_or("", operator_lexeme)
+}
@@ -0,0 +1,197 @@
+use std::{cell::RefCell, fmt, rc::Rc};
+
+use crate::{
+    class::LoxInstance,
+    environment::Environment,
+    interpreter::{Interpreter, RuntimeError, RuntimeErrorType, Signal, Value},
+    lexer::Token,
+    parser::Function,
+};
+
+/// Anything that can be called from Lox code. The interpreter checks the
+/// number of arguments against the arities before calling, `paren` is the
+/// closing parenthesis of the call, for the errors that are reported at the
+/// call.
+pub trait Callable: fmt::Display {
+    fn arity(&self) -> usize;
+
+    /// Fewest arguments it can be called with, parameters with a default
+    /// value can be left out.
+    fn min_arity(&self) -> usize {
+        self.arity()
+    }
+
+    /// Takes any number of arguments after the first `arity` ones.
+    fn is_variadic(&self) -> bool {
+        false
+    }
+
+    fn call(
+        self: Rc<Self>,
+        interpreter: &mut Interpreter,
+        arguments: Vec<Value>,
+        paren: &Token,
+    ) -> Result<Value, RuntimeError>;
+}
+
+impl fmt::Debug for dyn Callable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self}")
+    }
+}
+
+/// Function declared in Lox code, along with the environment it was declared
+/// in so that it can access the variables around it.
+pub struct LoxFunction {
+    declaration: Rc<Function>,
+    closure: Rc<RefCell<Environment>>,
+    // `init` methods always return `this`
+    is_initializer: bool,
+}
+
+impl LoxFunction {
+    pub fn new(
+        declaration: Rc<Function>,
+        closure: Rc<RefCell<Environment>>,
+        is_initializer: bool,
+    ) -> Self {
+        Self {
+            declaration,
+            closure,
+            is_initializer,
+        }
+    }
+
+    /// Getters are methods without a parameter list, they are called when
+    /// the property is accessed.
+    pub fn is_getter(&self) -> bool {
+        self.declaration.is_getter
+    }
+
+    /// Creates a copy of the method whose closure defines `this` as the
+    /// instance it was accessed on.
+    pub fn bind(&self, instance: LoxInstance) -> Self {
+        let mut environment = Environment::new(Rc::clone(&self.closure));
+        environment.define("this", Value::Instance(instance));
+
+        Self::new(
+            Rc::clone(&self.declaration),
+            Rc::new(RefCell::new(environment)),
+            self.is_initializer,
+        )
+    }
+}
+
+impl Callable for LoxFunction {
+    fn arity(&self) -> usize {
+        self.declaration.params.len()
+    }
+
+    fn min_arity(&self) -> usize {
+        self.declaration.required_params()
+    }
+
+    fn is_variadic(&self) -> bool {
+        self.declaration.rest.is_some()
+    }
+
+    fn call(
+        self: Rc<Self>,
+        interpreter: &mut Interpreter,
+        mut arguments: Vec<Value>,
+        _paren: &Token,
+    ) -> Result<Value, RuntimeError> {
+        let params = &self.declaration.params;
+        let rest = arguments.split_off(arguments.len().min(params.len()));
+
+        let given_defaults = arguments.len() - self.declaration.required_params();
+        for default in &self.declaration.defaults[given_defaults..] {
+            arguments.push(interpreter.evaluate_in(default, Rc::clone(&self.closure))?);
+        }
+
+        let mut environment = Environment::new(Rc::clone(&self.closure));
+
+        for (param, argument) in params.iter().zip(arguments) {
+            environment.define(param.name(), argument);
+        }
+
+        if let Some(param) = &self.declaration.rest {
+            environment.define(param.name(), Value::List(Rc::new(RefCell::new(rest))));
+        }
+
+        let environment = Rc::new(RefCell::new(environment));
+
+        let value = match interpreter.execute_block(&self.declaration.body, environment) {
+            Ok(()) => Value::Nil,
+            Err(Signal::Return(value)) => value,
+            Err(Signal::Error(error)) => return Err(error),
+            // carried through the expressions around the call as an error
+            Err(Signal::Throw { value, line }) => {
+                return Err(RuntimeError::new(RuntimeErrorType::Thrown(value), line))
+            }
+            Err(Signal::Break | Signal::Continue) => {
+                unreachable!("the resolver rejects loop control outside of loops")
+            }
+        };
+
+        if self.is_initializer {
+            return Ok(self
+                .closure
+                .borrow()
+                .lookup_at(0, "this")
+                .expect("initializers are bound to an instance"));
+        }
+
+        Ok(value)
+    }
+}
+
+impl fmt::Display for LoxFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.declaration.name.name() {
+            "" => write!(f, "<fn>"),
+            name => write!(f, "<fn {name}>"),
+        }
+    }
+}
+
+pub type NativeResult = Result<Value, RuntimeErrorType>;
+
+type NativeFn = dyn Fn(&[Value]) -> NativeResult;
+
+/// Function implemented in Rust and exposed to Lox code as a global, its
+/// errors are reported at the line of the call.
+pub struct NativeFunction {
+    arity: usize,
+    function: Box<NativeFn>,
+}
+
+impl NativeFunction {
+    pub fn new(arity: usize, function: impl Fn(&[Value]) -> NativeResult + 'static) -> Self {
+        Self {
+            arity,
+            function: Box::new(function),
+        }
+    }
+}
+
+impl Callable for NativeFunction {
+    fn arity(&self) -> usize {
+        self.arity
+    }
+
+    fn call(
+        self: Rc<Self>,
+        _interpreter: &mut Interpreter,
+        arguments: Vec<Value>,
+        paren: &Token,
+    ) -> Result<Value, RuntimeError> {
+        (self.function)(&arguments).map_err(|t| RuntimeError::new(t, paren.span().line))
+    }
+}
+
+impl fmt::Display for NativeFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<native fn>")
+    }
+}
@@ -0,0 +1,166 @@
+use std::{cell::RefCell, collections::HashMap, fmt, rc::Rc};
+
+use crate::{
+    callable::{Callable, LoxFunction},
+    interpreter::{Interpreter, RuntimeError, RuntimeErrorType, Value},
+    lexer::Token,
+};
+
+/// The method tables are flattened when the class is defined, they hold the
+/// inherited methods too so a lookup never walks the superclass chain.
+pub struct LoxClass {
+    name: String,
+    methods: HashMap<String, Rc<LoxFunction>>,
+    static_methods: HashMap<String, Rc<LoxFunction>>,
+}
+
+impl LoxClass {
+    /// The methods of the class override the ones of the same name it
+    /// inherits.
+    pub fn new(
+        name: &str,
+        superclass: Option<Rc<LoxClass>>,
+        methods: HashMap<String, Rc<LoxFunction>>,
+        static_methods: HashMap<String, Rc<LoxFunction>>,
+    ) -> Self {
+        let (mut all_methods, mut all_static_methods) = match superclass {
+            Some(superclass) => (
+                superclass.methods.clone(),
+                superclass.static_methods.clone(),
+            ),
+            None => (HashMap::new(), HashMap::new()),
+        };
+        all_methods.extend(methods);
+        all_static_methods.extend(static_methods);
+
+        Self {
+            name: name.to_owned(),
+            methods: all_methods,
+            static_methods: all_static_methods,
+        }
+    }
+
+    /// Same as `find_method`, for the methods called on the class itself.
+    pub fn find_static_method(&self, name: &str) -> Option<Rc<LoxFunction>> {
+        self.static_methods.get(name).cloned()
+    }
+
+    /// Looks the method up on the class, including the inherited ones.
+    pub fn find_method(&self, name: &str) -> Option<Rc<LoxFunction>> {
+        self.methods.get(name).cloned()
+    }
+}
+
+/// Calling a class creates a new instance of it, the arguments are passed on
+/// to its `init` method if it has one.
+impl Callable for LoxClass {
+    fn arity(&self) -> usize {
+        self.find_method("init")
+            .map_or(0, |initializer| initializer.arity())
+    }
+
+    fn min_arity(&self) -> usize {
+        self.find_method("init")
+            .map_or(0, |initializer| initializer.min_arity())
+    }
+
+    fn is_variadic(&self) -> bool {
+        self.find_method("init")
+            .is_some_and(|initializer| initializer.is_variadic())
+    }
+
+    fn call(
+        self: Rc<Self>,
+        interpreter: &mut Interpreter,
+        arguments: Vec<Value>,
+        paren: &Token,
+    ) -> Result<Value, RuntimeError> {
+        let instance = LoxInstance::new(Rc::clone(&self));
+
+        if let Some(initializer) = self.find_method("init") {
+            Rc::new(initializer.bind(instance.clone())).call(interpreter, arguments, paren)?;
+        }
+
+        Ok(Value::Instance(instance))
+    }
+}
+
+/// Shown as `<class Name>`, like functions are shown as `<fn name>`.
+impl fmt::Display for LoxClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<class {}>", self.name)
+    }
+}
+
+impl fmt::Debug for LoxClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self}")
+    }
+}
+
+/// Clones of an instance share its fields, so it is passed around by value.
+#[derive(Clone)]
+pub struct LoxInstance {
+    class: Rc<LoxClass>,
+    fields: Rc<RefCell<HashMap<String, Value>>>,
+}
+
+impl LoxInstance {
+    pub fn new(class: Rc<LoxClass>) -> Self {
+        Self {
+            class,
+            fields: Rc::new(RefCell::new(HashMap::new())),
+        }
+    }
+
+    pub fn is_same(&self, other: &LoxInstance) -> bool {
+        Rc::ptr_eq(&self.fields, &other.fields)
+    }
+
+    /// Fields shadow the methods of the class with the same name.
+    pub fn get(&self, name: &Token) -> Result<Value, RuntimeError> {
+        if let Some(value) = self.fields.borrow().get(name.name()) {
+            return Ok(value.clone());
+        }
+
+        if let Some(method) = self.class.find_method(name.name()) {
+            return Ok(Value::Callable(Rc::new(method.bind(self.clone()))));
+        }
+
+        Err(RuntimeError::new(
+            RuntimeErrorType::UndefinedProperty(name.name().to_owned()),
+            name.span().line,
+        ))
+    }
+
+    /// The getter with the name bound to the instance, unless a field
+    /// shadows it.
+    pub fn getter(&self, name: &str) -> Option<LoxFunction> {
+        if self.fields.borrow().contains_key(name) {
+            return None;
+        }
+
+        self.class
+            .find_method(name)
+            .filter(|method| method.is_getter())
+            .map(|method| method.bind(self.clone()))
+    }
+
+    pub fn set(&self, name: &Token, value: Value) {
+        self.fields
+            .borrow_mut()
+            .insert(name.name().to_owned(), value);
+    }
+}
+
+impl fmt::Display for LoxInstance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<instance {}>", self.class.name)
+    }
+}
+
+impl fmt::Debug for LoxInstance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self}")
+    }
+}
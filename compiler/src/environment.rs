@@ -0,0 +1,105 @@
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+use crate::{
+    interpreter::{RuntimeError, RuntimeErrorType, Value},
+    lexer::Token,
+};
+
+/// Variables of a single scope, lookups fall back to the enclosing scopes.
+#[derive(Default)]
+pub struct Environment {
+    values: HashMap<String, Value>,
+    enclosing: Option<Rc<RefCell<Environment>>>,
+}
+
+impl Environment {
+    pub fn new(enclosing: Rc<RefCell<Environment>>) -> Self {
+        Self {
+            values: HashMap::new(),
+            enclosing: Some(enclosing),
+        }
+    }
+
+    /// The variables of this scope only, in no particular order.
+    pub fn variables(&self) -> impl Iterator<Item = (&str, &Value)> {
+        self.values
+            .iter()
+            .map(|(name, value)| (name.as_str(), value))
+    }
+
+    pub fn define(&mut self, name: &str, value: Value) {
+        self.values.insert(name.to_owned(), value);
+    }
+
+    pub fn get(&self, name: &Token) -> Result<Value, RuntimeError> {
+        if let Some(value) = self.values.get(name.name()) {
+            return Ok(value.clone());
+        }
+
+        match &self.enclosing {
+            Some(enclosing) => enclosing.borrow().get(name),
+            None => Err(undefined_variable(name)),
+        }
+    }
+
+    /// Assigns to an existing variable, unlike `define` it never creates one.
+    pub fn assign(&mut self, name: &Token, value: Value) -> Result<(), RuntimeError> {
+        if let Some(variable) = self.values.get_mut(name.name()) {
+            *variable = value;
+            return Ok(());
+        }
+
+        match &self.enclosing {
+            Some(enclosing) => enclosing.borrow_mut().assign(name, value),
+            None => Err(undefined_variable(name)),
+        }
+    }
+
+    /// Reads a variable from the scope `distance` hops up the chain, as
+    /// computed by the resolver.
+    pub fn get_at(&self, distance: usize, name: &Token) -> Result<Value, RuntimeError> {
+        self.lookup_at(distance, name.name())
+            .ok_or_else(|| undefined_variable(name))
+    }
+
+    /// Same as `get_at`, for the variables that are defined by the
+    /// interpreter rather than declared by a token, like `this`.
+    pub fn lookup_at(&self, distance: usize, name: &str) -> Option<Value> {
+        if distance == 0 {
+            return self.values.get(name).cloned();
+        }
+
+        self.enclosing
+            .as_ref()
+            .and_then(|enclosing| enclosing.borrow().lookup_at(distance - 1, name))
+    }
+
+    pub fn assign_at(
+        &mut self,
+        distance: usize,
+        name: &Token,
+        value: Value,
+    ) -> Result<(), RuntimeError> {
+        if distance == 0 {
+            return match self.values.get_mut(name.name()) {
+                Some(variable) => {
+                    *variable = value;
+                    Ok(())
+                }
+                None => Err(undefined_variable(name)),
+            };
+        }
+
+        match &self.enclosing {
+            Some(enclosing) => enclosing.borrow_mut().assign_at(distance - 1, name, value),
+            None => Err(undefined_variable(name)),
+        }
+    }
+}
+
+fn undefined_variable(name: &Token) -> RuntimeError {
+    RuntimeError::new(
+        RuntimeErrorType::UndefinedVariable(name.name().to_owned()),
+        name.span().line,
+    )
+}
@@ -0,0 +1,127 @@
+use std::{error, fmt};
+
+use crate::{
+    interpreter::RuntimeError, lexer::LexingError, parser::ParseError, resolver::ResolveError,
+};
+
+/// Any error found while running a program, by the stage that found it.
+#[derive(Debug)]
+pub enum LoxError {
+    Lex(LexingError),
+    Parse(ParseError),
+    Resolve(ResolveError),
+    Runtime(RuntimeError),
+}
+
+impl LoxError {
+    pub fn line(&self) -> usize {
+        match self {
+            LoxError::Lex(error) => error.line(),
+            LoxError::Parse(error) => error.line(),
+            LoxError::Resolve(error) => error.line(),
+            LoxError::Runtime(error) => error.line(),
+        }
+    }
+
+    /// Only lexing and parse errors know their column so far.
+    pub fn column(&self) -> Option<usize> {
+        match self {
+            LoxError::Lex(error) => Some(error.column()),
+            LoxError::Parse(error) => Some(error.column()),
+            _ => None,
+        }
+    }
+
+    /// Only lexing and parse errors know the file given by a `//#line`
+    /// directive so far.
+    pub fn file(&self) -> Option<&str> {
+        match self {
+            LoxError::Lex(error) => error.file(),
+            LoxError::Parse(error) => error.file(),
+            _ => None,
+        }
+    }
+
+    /// The error as `file:line:column: Error: message`, the file named by a
+    /// `//#line` directive takes precedence over `file`. Errors that don't
+    /// know their column leave it out, and the error is formatted as
+    /// `Display` does if there is no file at all.
+    pub fn located(&self, file: Option<&str>) -> String {
+        let Some(file) = self.file().or(file) else {
+            return self.to_string();
+        };
+
+        match self.column() {
+            Some(column) => format!("{file}:{}:{column}: Error: {}", self.line(), self.message()),
+            None => format!("{file}:{}: Error: {}", self.line(), self.message()),
+        }
+    }
+
+    /// Exit status of a script that failed with this error, following the
+    /// sysexits convention of the book: 65 for errors in the program itself
+    /// and 70 for errors while running it.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            LoxError::Runtime(_) => 70,
+            _ => 65,
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            LoxError::Lex(error) => error.error_type().to_string(),
+            LoxError::Parse(error) => error.error_type().to_string(),
+            LoxError::Resolve(error) => error.error_type().to_string(),
+            LoxError::Runtime(error) => error.error_type().to_string(),
+        }
+    }
+}
+
+impl fmt::Display for LoxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.file() {
+            Some(file) => write!(
+                f,
+                "[line {} in {file}] Error: {}",
+                self.line(),
+                self.message()
+            ),
+            None => write!(f, "[line {}] Error: {}", self.line(), self.message()),
+        }
+    }
+}
+
+impl error::Error for LoxError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            LoxError::Lex(error) => Some(error),
+            LoxError::Parse(error) => Some(error),
+            LoxError::Resolve(error) => Some(error),
+            LoxError::Runtime(error) => Some(error),
+        }
+    }
+}
+
+impl From<LexingError> for LoxError {
+    fn from(error: LexingError) -> Self {
+        LoxError::Lex(error)
+    }
+}
+
+impl From<ParseError> for LoxError {
+    fn from(error: ParseError) -> Self {
+        LoxError::Parse(error)
+    }
+}
+
+impl From<ResolveError> for LoxError {
+    fn from(error: ResolveError) -> Self {
+        LoxError::Resolve(error)
+    }
+}
+
+impl From<RuntimeError> for LoxError {
+    fn from(error: RuntimeError) -> Self {
+        LoxError::Runtime(error)
+    }
+}
@@ -0,0 +1,241 @@
+use std::{mem, rc::Rc};
+
+use crate::{
+    lexer::TokenType,
+    parser::{Expr, Function, Literal, Stmt},
+};
+
+/// Folds the operations on literals in the program into the literals they
+/// evaluate to. `Lox` runs it only right before the program is resolved,
+/// and not when tracing, so that the printed trees are the ones written.
+pub fn fold_program(statements: &mut [Stmt]) {
+    for stmt in statements {
+        fold_stmt(stmt);
+    }
+}
+
+/// Same as `fold_program`, for an expression entered in the promt.
+pub fn fold_expression(expr: &mut Expr) {
+    if let Expr::Lambda(function) = expr {
+        fold_function(function);
+        return;
+    }
+
+    for child in children(expr) {
+        fold_expression(child);
+    }
+
+    *expr = fold_constants(mem::replace(expr, Expr::Literal(Literal::Nil)));
+}
+
+fn fold_stmt(stmt: &mut Stmt) {
+    match stmt {
+        Stmt::Expression(expr) | Stmt::Print(expr) => fold_expression(expr),
+        Stmt::Assert {
+            condition, message, ..
+        } => {
+            fold_expression(condition);
+            if let Some(message) = message {
+                fold_expression(message);
+            }
+        }
+        Stmt::Var { initializer, .. } => {
+            if let Some(initializer) = initializer {
+                fold_expression(initializer);
+            }
+        }
+        Stmt::Block(statements) => fold_program(statements),
+        Stmt::If {
+            condition,
+            then_branch,
+            else_branch,
+        } => {
+            fold_expression(condition);
+            fold_stmt(then_branch);
+            if let Some(else_branch) = else_branch {
+                fold_stmt(else_branch);
+            }
+        }
+        Stmt::While {
+            condition,
+            body,
+            increment,
+        } => {
+            fold_expression(condition);
+            fold_stmt(body);
+            if let Some(increment) = increment {
+                fold_expression(increment);
+            }
+        }
+        Stmt::DoWhile { body, condition } => {
+            fold_stmt(body);
+            fold_expression(condition);
+        }
+        Stmt::ForIn { iterable, body, .. } => {
+            fold_expression(iterable);
+            fold_stmt(body);
+        }
+        Stmt::Switch {
+            subject,
+            cases,
+            default,
+        } => {
+            fold_expression(subject);
+            for (value, body) in cases {
+                fold_expression(value);
+                fold_program(body);
+            }
+            if let Some(body) = default {
+                fold_program(body);
+            }
+        }
+        Stmt::Throw { value, .. } => fold_expression(value),
+        Stmt::Try { body, handler, .. } => {
+            fold_program(body);
+            fold_program(handler);
+        }
+        Stmt::Function(function) => fold_function(function),
+        Stmt::Class {
+            superclass,
+            methods,
+            static_methods,
+            ..
+        } => {
+            if let Some(superclass) = superclass {
+                fold_expression(superclass);
+            }
+            for method in methods.iter_mut().chain(static_methods) {
+                fold_function(method);
+            }
+        }
+        Stmt::Return { value, .. } => {
+            if let Some(value) = value {
+                fold_expression(value);
+            }
+        }
+        Stmt::Include { .. } | Stmt::Break(_) | Stmt::Continue(_) => {}
+    }
+}
+
+// the functions are only shared once the program runs, one that is shared
+// already is left as it is
+fn fold_function(function: &mut Rc<Function>) {
+    let Some(function) = Rc::get_mut(function) else {
+        return;
+    };
+
+    for default in &mut function.defaults {
+        fold_expression(default);
+    }
+    fold_program(&mut function.body);
+}
+
+/// The subexpressions of the expression, in the order they are evaluated.
+fn children(expr: &mut Expr) -> Vec<&mut Expr> {
+    match expr {
+        Expr::Binary { left, right, .. } | Expr::Logical { left, right, .. } => {
+            vec![&mut **left, &mut **right]
+        }
+        Expr::Unary { right: expr, .. }
+        | Expr::Postfix { left: expr, .. }
+        | Expr::Grouping(expr)
+        | Expr::Get { object: expr, .. }
+        | Expr::Assign { value: expr, .. } => vec![&mut **expr],
+        Expr::Call {
+            callee, arguments, ..
+        } => std::iter::once(&mut **callee).chain(arguments).collect(),
+        Expr::Set { object, value, .. } => vec![&mut **object, &mut **value],
+        Expr::Interpolation(parts) => parts.iter_mut().collect(),
+        Expr::Conditional {
+            condition,
+            then_branch,
+            else_branch,
+        } => vec![&mut **condition, &mut **then_branch, &mut **else_branch],
+        Expr::List { elements, .. } => elements.iter_mut().collect(),
+        Expr::Map { entries, .. } => entries
+            .iter_mut()
+            .flat_map(|(key, value)| [key, value])
+            .collect(),
+        Expr::Index { object, index, .. } => vec![&mut **object, &mut **index],
+        Expr::SetIndex {
+            object,
+            index,
+            value,
+            ..
+        } => vec![&mut **object, &mut **index, &mut **value],
+        Expr::Literal(_)
+        | Expr::Variable { .. }
+        | Expr::This { .. }
+        | Expr::Super { .. }
+        | Expr::Lambda(_) => Vec::new(),
+    }
+}
+
+/// Folds an operation on literals into the literal it evaluates to, the ones
+/// that would fail are kept so that they fail at runtime like any other.
+/// The children are expected to be folded already.
+fn fold_constants(expr: Expr) -> Expr {
+    // a folded constant doesn't need the grouping anymore
+    let expr = match expr {
+        Expr::Grouping(inner) if matches!(*inner, Expr::Literal(_)) => return *inner,
+        expr => expr,
+    };
+
+    let literal = match &expr {
+        Expr::Binary {
+            left,
+            operator,
+            right,
+        } => match (&**left, operator.token_type(), &**right) {
+            (
+                Expr::Literal(Literal::Number(left)),
+                operator_type,
+                Expr::Literal(Literal::Number(right)),
+            ) => match operator_type {
+                TokenType::Plus => Literal::Number(left + right),
+                TokenType::Minus => Literal::Number(left - right),
+                TokenType::Star => Literal::Number(left * right),
+                TokenType::Slash if *right != 0.0 => Literal::Number(left / right),
+                TokenType::Percent if *right != 0.0 => Literal::Number(left % right),
+                _ => return expr,
+            },
+            // only folded when the result fits, the interpreter promotes it
+            // to a float otherwise
+            (
+                Expr::Literal(Literal::Int(left)),
+                operator_type,
+                Expr::Literal(Literal::Int(right)),
+            ) => {
+                let integer = match operator_type {
+                    TokenType::Plus => left.checked_add(*right),
+                    TokenType::Minus => left.checked_sub(*right),
+                    TokenType::Star => left.checked_mul(*right),
+                    _ => None,
+                };
+
+                match integer {
+                    Some(integer) => Literal::Int(integer),
+                    None => return expr,
+                }
+            }
+            (
+                Expr::Literal(Literal::String(left)),
+                TokenType::Plus,
+                Expr::Literal(Literal::String(right)),
+            ) => Literal::String(format!("{left}{right}")),
+            _ => return expr,
+        },
+        Expr::Unary { operator, right } => match (operator.token_type(), &**right) {
+            (TokenType::Minus, Expr::Literal(Literal::Number(number))) => Literal::Number(-number),
+            (TokenType::Minus, Expr::Literal(Literal::Int(integer))) => match integer.checked_neg()
+            {
+                Some(integer) => Literal::Int(integer),
+                None => return expr,
+            },
+            _ => return expr,
+        },
+        _ => return expr,
+    };
+
+    Expr::Literal(literal)
+}
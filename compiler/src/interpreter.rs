@@ -0,0 +1,1433 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fmt,
+    io::{self, Write},
+    rc::Rc,
+};
+
+use log::{debug, warn};
+
+use crate::{
+    ast_printer,
+    callable::{Callable, LoxFunction, NativeFunction, NativeResult},
+    class::{LoxClass, LoxInstance},
+    environment::Environment,
+    lexer::{Token, TokenType},
+    natives,
+    parser::{Expr, ExprId, Function, Literal, Stmt},
+};
+
+/// Integers and floats are both numbers to the program, an operation on
+/// integers gives an integer unless the result doesn't fit in one.
+#[derive(Debug, Clone)]
+pub enum Value {
+    Int(i64),
+    Number(f64),
+    Str(String),
+    Bool(bool),
+    Nil,
+    Callable(Rc<dyn Callable>),
+    Class(Rc<LoxClass>),
+    Instance(LoxInstance),
+    List(List),
+    Map(Map),
+}
+
+/// Lists and maps are shared, so that changes are seen through every
+/// reference.
+pub type List = Rc<RefCell<Vec<Value>>>;
+pub type Map = Rc<RefCell<HashMap<MapKey, Value>>>;
+
+/// Only the values that are compared by content can be map keys, floats are
+/// kept as their bits because `f64` isn't hashable. Functions, classes,
+/// instances, lists and maps are compared by identity, so they can't be.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum MapKey {
+    Int(i64),
+    Number(u64),
+    Str(String),
+    Bool(bool),
+    Nil,
+}
+
+impl MapKey {
+    fn new(value: &Value, token: &Token) -> Result<Self, RuntimeError> {
+        match value {
+            // `1 == 1.0` and `-0.0 == 0.0`, so integral floats are the same
+            // key as the integer they equal
+            Value::Int(_) | Value::Number(_) => Ok(match value.as_integer() {
+                Some(integer) => MapKey::Int(integer),
+                None => {
+                    let number = value.as_number().unwrap_or_default();
+                    // `NaN` isn't equal to itself, but every `NaN` is the same
+                    // key so that it can be looked up again
+                    let number = if number.is_nan() { f64::NAN } else { number };
+
+                    MapKey::Number(number.to_bits())
+                }
+            }),
+            Value::Str(string) => Ok(MapKey::Str(string.clone())),
+            Value::Bool(bool) => Ok(MapKey::Bool(*bool)),
+            Value::Nil => Ok(MapKey::Nil),
+            _ => Err(RuntimeError::new(
+                RuntimeErrorType::InvalidMapKey,
+                token.span().line,
+            )),
+        }
+    }
+}
+
+impl fmt::Display for MapKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MapKey::Int(integer) => write!(f, "{integer}"),
+            MapKey::Number(bits) => write!(f, "{}", f64::from_bits(*bits)),
+            MapKey::Str(string) => write!(f, "{string}"),
+            MapKey::Bool(bool) => write!(f, "{bool}"),
+            MapKey::Nil => write!(f, "nil"),
+        }
+    }
+}
+
+impl Value {
+    /// `nil` and `false` are falsey, every other value is truthy.
+    pub fn is_truthy(&self) -> bool {
+        !matches!(self, Value::Nil | Value::Bool(false))
+    }
+
+    /// The value of either kind of number as a float.
+    pub fn as_number(&self) -> Option<f64> {
+        match self {
+            Value::Int(integer) => Some(*integer as f64),
+            Value::Number(number) => Some(*number),
+            _ => None,
+        }
+    }
+
+    /// The value of an integer, or of a float that is an integer within the
+    /// range of `i64`.
+    pub fn as_integer(&self) -> Option<i64> {
+        match self {
+            Value::Int(integer) => Some(*integer),
+            Value::Number(number) => {
+                let in_range = *number >= i64::MIN as f64 && *number < i64::MAX as f64;
+
+                (number.fract() == 0.0 && in_range).then_some(*number as i64)
+            }
+            _ => None,
+        }
+    }
+
+    /// Same as `Display`, but floats, including the ones in lists and maps,
+    /// are rounded to `precision` decimal places if it is given.
+    pub fn display_with(&self, precision: Option<usize>) -> ValueDisplay<'_> {
+        ValueDisplay {
+            value: self,
+            precision,
+        }
+    }
+
+    /// Name of the kind of value, as given by `typeof`.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Int(_) | Value::Number(_) => "number",
+            Value::Str(_) => "string",
+            Value::Bool(_) => "boolean",
+            Value::Nil => "nil",
+            Value::Callable(_) => "function",
+            Value::Class(_) => "class",
+            Value::Instance(_) => "instance",
+            Value::List(_) => "list",
+            Value::Map(_) => "map",
+        }
+    }
+}
+
+/// Values of different types are never equal, so comparing them never fails.
+/// Numbers follow IEEE 754, so `NaN` isn't equal to anything, itself
+/// included, and every ordering with it is false.
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Value::Int(left), Value::Int(right)) => left == right,
+            (Value::Int(_) | Value::Number(_), Value::Int(_) | Value::Number(_)) => {
+                self.as_number() == other.as_number()
+            }
+            (Value::Str(left), Value::Str(right)) => left == right,
+            (Value::Bool(left), Value::Bool(right)) => left == right,
+            (Value::Nil, Value::Nil) => true,
+            (Value::Callable(left), Value::Callable(right)) => Rc::ptr_eq(left, right),
+            (Value::Class(left), Value::Class(right)) => Rc::ptr_eq(left, right),
+            (Value::Instance(left), Value::Instance(right)) => left.is_same(right),
+            (Value::List(left), Value::List(right)) => Rc::ptr_eq(left, right),
+            (Value::Map(left), Value::Map(right)) => Rc::ptr_eq(left, right),
+            _ => false,
+        }
+    }
+}
+
+/// Formats values the way `print` shows them, so integral numbers have no
+/// trailing `.0` and strings have no quotes. Floats are written in the
+/// shortest form that reads back as the same number.
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.display_with(None))
+    }
+}
+
+/// See `Value::display_with`.
+pub struct ValueDisplay<'a> {
+    value: &'a Value,
+    precision: Option<usize>,
+}
+
+impl fmt::Display for ValueDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let precision = self.precision;
+
+        match self.value {
+            Value::Int(integer) => write!(f, "{integer}"),
+            Value::Number(number) => match precision {
+                Some(precision) => write!(f, "{number:.precision$}"),
+                None => write!(f, "{number}"),
+            },
+            Value::Str(string) => write!(f, "{string}"),
+            Value::Bool(bool) => write!(f, "{bool}"),
+            Value::Nil => write!(f, "nil"),
+            Value::Callable(callable) => write!(f, "{callable}"),
+            Value::Class(class) => write!(f, "{class}"),
+            Value::Instance(instance) => write!(f, "{instance}"),
+            Value::List(list) => {
+                write!(f, "[")?;
+
+                for (index, element) in list.borrow().iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+
+                    write!(f, "{}", element.display_with(precision))?;
+                }
+
+                write!(f, "]")
+            }
+            Value::Map(map) => {
+                // sorted, as the order of a hash map changes from run to run
+                let mut entries = map
+                    .borrow()
+                    .iter()
+                    .map(|(key, value)| format!("{key}: {}", value.display_with(precision)))
+                    .collect::<Vec<_>>();
+                entries.sort();
+
+                write!(f, "{{{}}}", entries.join(", "))
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum RuntimeErrorType {
+    OperandMustBeNumber,
+    OperandsMustBeNumbers,
+    OperandsMustBeNumbersOrStrings,
+    DivisionByZero,
+    UndefinedVariable(String),
+    NotCallable,
+    ArityMismatch { expected: usize, got: usize },
+    ArityRangeMismatch { min: usize, max: usize, got: usize },
+    TooFewArguments { min: usize, got: usize },
+    UndefinedProperty(String),
+    GetterCalled(String),
+    OnlyInstancesHaveProperties,
+    OnlyInstancesHaveFields,
+    SuperclassMustBeClass,
+    // raised by native functions, which explain what they expected
+    InvalidArgument(&'static str),
+    IndexOutOfRange,
+    IndexMustBeInteger,
+    NotIndexable,
+    InvalidMapKey,
+    StackOverflow,
+    AssertionFailed(Option<String>),
+    NotIterable,
+    // a thrown value that no `try` caught
+    Thrown(Value),
+    OperandsMustBeIntegers,
+    InvalidShift,
+    OperandMustBeNonNegativeInteger,
+}
+
+#[derive(Debug)]
+pub struct RuntimeError {
+    t: RuntimeErrorType,
+    line: usize,
+}
+
+impl RuntimeError {
+    pub fn new(t: RuntimeErrorType, line: usize) -> Self {
+        Self { t, line }
+    }
+
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    pub fn error_type(&self) -> &RuntimeErrorType {
+        &self.t
+    }
+}
+
+impl fmt::Display for RuntimeErrorType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RuntimeErrorType::OperandMustBeNumber => write!(f, "Operand must be a number."),
+            RuntimeErrorType::OperandsMustBeNumbers => write!(f, "Operands must be numbers."),
+            RuntimeErrorType::OperandsMustBeNumbersOrStrings => {
+                write!(f, "Operands must be two numbers or two strings.")
+            }
+            RuntimeErrorType::DivisionByZero => write!(f, "Division by zero."),
+            RuntimeErrorType::UndefinedVariable(name) => write!(f, "Undefined variable '{name}'."),
+            RuntimeErrorType::NotCallable => write!(f, "Can only call functions and classes."),
+            RuntimeErrorType::ArityMismatch { expected, got } => {
+                write!(f, "Expected {expected} arguments but got {got}.")
+            }
+            RuntimeErrorType::ArityRangeMismatch { min, max, got } => {
+                write!(f, "Expected {min} to {max} arguments but got {got}.")
+            }
+            RuntimeErrorType::TooFewArguments { min, got } => {
+                write!(f, "Expected at least {min} arguments but got {got}.")
+            }
+            RuntimeErrorType::UndefinedProperty(name) => write!(f, "Undefined property '{name}'."),
+            RuntimeErrorType::GetterCalled(name) => {
+                write!(
+                    f,
+                    "Getter '{name}' can't be called, read it without parentheses."
+                )
+            }
+            RuntimeErrorType::OnlyInstancesHaveProperties => {
+                write!(f, "Only instances have properties.")
+            }
+            RuntimeErrorType::OnlyInstancesHaveFields => write!(f, "Only instances have fields."),
+            RuntimeErrorType::SuperclassMustBeClass => write!(f, "Superclass must be a class."),
+            RuntimeErrorType::InvalidArgument(message) => write!(f, "{message}"),
+            RuntimeErrorType::IndexOutOfRange => write!(f, "Index out of range."),
+            RuntimeErrorType::IndexMustBeInteger => write!(f, "Index must be an integer."),
+            RuntimeErrorType::NotIndexable => write!(f, "Can only index lists and maps."),
+            RuntimeErrorType::StackOverflow => write!(f, "Stack overflow."),
+            RuntimeErrorType::AssertionFailed(Some(message)) => {
+                write!(f, "Assertion failed: {message}")
+            }
+            RuntimeErrorType::AssertionFailed(None) => write!(f, "Assertion failed."),
+            RuntimeErrorType::Thrown(value) => write!(f, "Uncaught exception: {value}"),
+            RuntimeErrorType::NotIterable => {
+                write!(f, "Can only iterate over lists and strings.")
+            }
+            RuntimeErrorType::OperandsMustBeIntegers => write!(f, "Operands must be integers."),
+            RuntimeErrorType::InvalidShift => {
+                write!(f, "Shift amount must be between 0 and 63.")
+            }
+            RuntimeErrorType::OperandMustBeNonNegativeInteger => {
+                write!(f, "Operand must be a non-negative integer.")
+            }
+            RuntimeErrorType::InvalidMapKey => {
+                write!(f, "Map keys must be numbers, strings, booleans or nil.")
+            }
+        }
+    }
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[line {}] Error: {}", self.line, self.t)
+    }
+}
+
+impl std::error::Error for RuntimeError {}
+
+/// Interrupts the execution of statements, either because of an error or to
+/// unwind to the enclosing function call or loop.
+#[derive(Debug)]
+pub enum Signal {
+    Error(RuntimeError),
+    // unwinds to the closest `try`, `line` is where it was thrown
+    Throw { value: Value, line: usize },
+    Return(Value),
+    Break,
+    Continue,
+}
+
+/// A value thrown in a function comes out of the call as an error, so it is
+/// turned back into a throw for the `try` around the call to catch it.
+impl From<RuntimeError> for Signal {
+    fn from(error: RuntimeError) -> Self {
+        match error.t {
+            RuntimeErrorType::Thrown(value) => Signal::Throw {
+                value,
+                line: error.line,
+            },
+            _ => Signal::Error(error),
+        }
+    }
+}
+
+/// Deep enough for most recursion. `Lox` runs the interpreter on a stack
+/// sized for it, an interpreter driven directly needs about 16 KiB of stack
+/// per call in debug builds and a quarter of that in release builds.
+pub const DEFAULT_MAX_CALL_DEPTH: usize = 1000;
+
+pub struct Interpreter {
+    globals: Rc<RefCell<Environment>>,
+    environment: Rc<RefCell<Environment>>,
+    // scope distances of the local variables, filled in by the resolver
+    locals: HashMap<ExprId, usize>,
+    // where `print` writes to
+    output: Box<dyn Write>,
+    // logs every statement before it is executed
+    trace: bool,
+    // decimal places `print` rounds floats to, see `Value::display_with`
+    precision: Option<usize>,
+    // number of calls that haven't returned yet, limited so that runaway
+    // recursion is an error rather than an overflow of the Rust stack
+    call_depth: usize,
+    max_call_depth: usize,
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new(Box::new(io::stdout()))
+    }
+}
+
+impl Interpreter {
+    pub fn new(output: Box<dyn Write>) -> Self {
+        let globals = Rc::new(RefCell::new(Environment::default()));
+
+        let mut interpreter = Self {
+            environment: Rc::clone(&globals),
+            globals,
+            locals: HashMap::new(),
+            output,
+            trace: false,
+            precision: None,
+            call_depth: 0,
+            max_call_depth: DEFAULT_MAX_CALL_DEPTH,
+        };
+
+        natives::define_natives(&mut interpreter);
+
+        interpreter
+    }
+
+    /// Logs every statement at debug level before it is executed, along with
+    /// the values of expression statements.
+    pub fn set_trace(&mut self, trace: bool) {
+        self.trace = trace;
+    }
+
+    pub fn trace(&self) -> bool {
+        self.trace
+    }
+
+    /// Rounds the floats that `print` writes to `precision` decimal places,
+    /// `None` writes them in full.
+    pub fn set_precision(&mut self, precision: Option<usize>) {
+        self.precision = precision;
+    }
+
+    pub fn precision(&self) -> Option<usize> {
+        self.precision
+    }
+
+    /// Raises or lowers `DEFAULT_MAX_CALL_DEPTH`, for callers that know how
+    /// big the stack they run on is.
+    pub fn set_max_call_depth(&mut self, max_call_depth: usize) {
+        self.max_call_depth = max_call_depth;
+    }
+
+    /// Gives back the output, once the interpreter is done with it.
+    pub fn into_output(self) -> Box<dyn Write> {
+        self.output
+    }
+
+    /// Where `print` writes to, so that other output can be interleaved
+    /// with it.
+    pub fn output(&mut self) -> &mut dyn Write {
+        &mut self.output
+    }
+
+    /// The global variables sorted by name, natives included.
+    pub fn globals(&self) -> Vec<(String, Value)> {
+        let mut globals = self
+            .globals
+            .borrow()
+            .variables()
+            .map(|(name, value)| (name.to_owned(), value.clone()))
+            .collect::<Vec<_>>();
+        globals.sort_by(|(left, _), (right, _)| left.cmp(right));
+
+        globals
+    }
+
+    /// Registers a Rust function as a global that Lox code can call.
+    pub fn define_native(
+        &mut self,
+        name: &str,
+        arity: usize,
+        function: impl Fn(&[Value]) -> NativeResult + 'static,
+    ) {
+        let native = NativeFunction::new(arity, function);
+
+        self.globals
+            .borrow_mut()
+            .define(name, Value::Callable(Rc::new(native)));
+    }
+
+    pub fn resolve(&mut self, id: ExprId, depth: usize) {
+        self.locals.insert(id, depth);
+    }
+
+    pub fn interpret(&mut self, statements: &[Stmt]) -> Result<(), RuntimeError> {
+        for stmt in statements {
+            match self.execute(stmt) {
+                Ok(()) => {}
+                Err(Signal::Error(error)) => return Err(error),
+                Err(Signal::Throw { value, line }) => {
+                    return Err(RuntimeError::new(RuntimeErrorType::Thrown(value), line))
+                }
+                // the resolver rejects these at the top level, so nothing is left to unwind to
+                Err(Signal::Return(_) | Signal::Break | Signal::Continue) => break,
+            }
+        }
+
+        Ok(())
+    }
+
+    fn execute(&mut self, stmt: &Stmt) -> Result<(), Signal> {
+        if self.trace {
+            trace_stmt(stmt);
+        }
+
+        match stmt {
+            Stmt::Expression(expr) => {
+                let value = self.evaluate(expr)?;
+
+                if self.trace {
+                    trace_value(&value);
+                }
+
+                Ok(())
+            }
+            Stmt::Print(expr) => {
+                let value = self.evaluate(expr)?;
+                self.print(&value);
+
+                Ok(())
+            }
+            Stmt::Assert {
+                keyword,
+                condition,
+                message,
+            } => self.execute_assert(keyword, condition, message.as_ref()),
+            Stmt::Var { name, initializer } => {
+                let value = match initializer {
+                    Some(initializer) => self.evaluate(initializer)?,
+                    None => Value::Nil,
+                };
+
+                self.environment.borrow_mut().define(name.name(), value);
+
+                Ok(())
+            }
+            Stmt::Block(statements) => {
+                let environment = Environment::new(Rc::clone(&self.environment));
+                self.execute_block(statements, Rc::new(RefCell::new(environment)))
+            }
+            Stmt::If {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                if self.evaluate(condition)?.is_truthy() {
+                    self.execute(then_branch)
+                } else if let Some(else_branch) = else_branch {
+                    self.execute(else_branch)
+                } else {
+                    Ok(())
+                }
+            }
+            Stmt::While {
+                condition,
+                body,
+                increment,
+            } => self.execute_while(condition, body, increment.as_ref()),
+            Stmt::DoWhile { body, condition } => self.execute_do_while(body, condition),
+            Stmt::ForIn {
+                name,
+                iterable,
+                body,
+            } => self.execute_for_in(name, iterable, body),
+            Stmt::Switch {
+                subject,
+                cases,
+                default,
+            } => self.execute_switch(subject, cases, default.as_deref()),
+            Stmt::Throw { keyword, value } => {
+                let value = self.evaluate(value)?;
+
+                Err(Signal::Throw {
+                    value,
+                    line: keyword.span().line,
+                })
+            }
+            Stmt::Try {
+                body,
+                name,
+                handler,
+            } => self.execute_try(body, name, handler),
+            Stmt::Break(_) => Err(Signal::Break),
+            Stmt::Continue(_) => Err(Signal::Continue),
+            Stmt::Function(declaration) => {
+                let function =
+                    LoxFunction::new(Rc::clone(declaration), Rc::clone(&self.environment), false);
+
+                self.environment
+                    .borrow_mut()
+                    .define(declaration.name.name(), Value::Callable(Rc::new(function)));
+
+                Ok(())
+            }
+            Stmt::Class {
+                name,
+                superclass,
+                methods,
+                static_methods,
+            } => self.define_class(name, superclass.as_ref(), methods, static_methods),
+            // expanded by `Lox` before the program is run
+            Stmt::Include { .. } => Ok(()),
+            Stmt::Return { value, .. } => {
+                let value = match value {
+                    Some(value) => self.evaluate(value)?,
+                    None => Value::Nil,
+                };
+
+                Err(Signal::Return(value))
+            }
+        }
+    }
+
+    fn execute_while(
+        &mut self,
+        condition: &Expr,
+        body: &Stmt,
+        increment: Option<&Expr>,
+    ) -> Result<(), Signal> {
+        while self.evaluate(condition)?.is_truthy() {
+            match self.execute(body) {
+                Ok(()) | Err(Signal::Continue) => {}
+                Err(Signal::Break) => break,
+                Err(signal) => return Err(signal),
+            }
+
+            if let Some(increment) = increment {
+                self.evaluate(increment)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn execute_do_while(&mut self, body: &Stmt, condition: &Expr) -> Result<(), Signal> {
+        loop {
+            match self.execute(body) {
+                Ok(()) | Err(Signal::Continue) => {}
+                Err(Signal::Break) => break,
+                Err(signal) => return Err(signal),
+            }
+
+            if !self.evaluate(condition)?.is_truthy() {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn print(&mut self, value: &Value) {
+        if let Err(error) = writeln!(self.output, "{}", value.display_with(self.precision)) {
+            warn!("Could not write the output: {error}");
+        }
+    }
+
+    fn execute_assert(
+        &mut self,
+        keyword: &Token,
+        condition: &Expr,
+        message: Option<&Expr>,
+    ) -> Result<(), Signal> {
+        if !self.evaluate(condition)?.is_truthy() {
+            let message = match message {
+                Some(message) => Some(self.evaluate(message)?.to_string()),
+                None => None,
+            };
+
+            return Err(RuntimeError::new(
+                RuntimeErrorType::AssertionFailed(message),
+                keyword.span().line,
+            )
+            .into());
+        }
+
+        Ok(())
+    }
+
+    fn execute_for_in(&mut self, name: &Token, iterable: &Expr, body: &Stmt) -> Result<(), Signal> {
+        // the elements are copied, so that the body can change the list
+        let elements = match self.evaluate(iterable)? {
+            Value::List(list) => list.borrow().clone(),
+            Value::Str(string) => string
+                .chars()
+                .map(|char| Value::Str(char.to_string()))
+                .collect(),
+            _ => {
+                return Err(
+                    RuntimeError::new(RuntimeErrorType::NotIterable, name.span().line).into(),
+                )
+            }
+        };
+
+        for element in elements {
+            let mut environment = Environment::new(Rc::clone(&self.environment));
+            environment.define(name.name(), element);
+
+            let environment = Rc::new(RefCell::new(environment));
+            match self.execute_block(std::slice::from_ref(body), environment) {
+                Ok(()) | Err(Signal::Continue) => {}
+                Err(Signal::Break) => break,
+                Err(signal) => return Err(signal),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn execute_switch(
+        &mut self,
+        subject: &Expr,
+        cases: &[(Expr, Vec<Stmt>)],
+        default: Option<&[Stmt]>,
+    ) -> Result<(), Signal> {
+        let subject = self.evaluate(subject)?;
+
+        let mut body = default;
+        for (value, case_body) in cases {
+            if self.evaluate(value)? == subject {
+                body = Some(case_body);
+                break;
+            }
+        }
+
+        if let Some(body) = body {
+            let environment = Environment::new(Rc::clone(&self.environment));
+            self.execute_block(body, Rc::new(RefCell::new(environment)))?;
+        }
+
+        Ok(())
+    }
+
+    fn execute_try(&mut self, body: &[Stmt], name: &Token, handler: &[Stmt]) -> Result<(), Signal> {
+        let environment = Environment::new(Rc::clone(&self.environment));
+
+        match self.execute_block(body, Rc::new(RefCell::new(environment))) {
+            Err(Signal::Throw { value, .. }) => {
+                let mut environment = Environment::new(Rc::clone(&self.environment));
+                environment.define(name.name(), value);
+
+                self.execute_block(handler, Rc::new(RefCell::new(environment)))?;
+            }
+            result => result?,
+        }
+
+        Ok(())
+    }
+
+    fn define_class(
+        &mut self,
+        name: &Token,
+        superclass: Option<&Expr>,
+        methods: &[Rc<Function>],
+        static_methods: &[Rc<Function>],
+    ) -> Result<(), Signal> {
+        let superclass = match superclass {
+            Some(superclass) => match self.evaluate(superclass)? {
+                Value::Class(superclass) => Some(superclass),
+                _ => {
+                    return Err(RuntimeError::new(
+                        RuntimeErrorType::SuperclassMustBeClass,
+                        name.span().line,
+                    )
+                    .into())
+                }
+            },
+            None => None,
+        };
+
+        let static_methods = static_methods
+            .iter()
+            .map(|method| {
+                let function =
+                    LoxFunction::new(Rc::clone(method), Rc::clone(&self.environment), false);
+
+                (method.name.name().to_owned(), Rc::new(function))
+            })
+            .collect();
+
+        // the methods close over an extra scope that holds `super`
+        let enclosing = Rc::clone(&self.environment);
+        if let Some(superclass) = &superclass {
+            let mut environment = Environment::new(Rc::clone(&enclosing));
+            environment.define("super", Value::Class(Rc::clone(superclass)));
+
+            self.environment = Rc::new(RefCell::new(environment));
+        }
+
+        let methods = methods
+            .iter()
+            .map(|method| {
+                let function = LoxFunction::new(
+                    Rc::clone(method),
+                    Rc::clone(&self.environment),
+                    method.name.name() == "init",
+                );
+
+                (method.name.name().to_owned(), Rc::new(function))
+            })
+            .collect();
+
+        let class = LoxClass::new(name.name(), superclass, methods, static_methods);
+
+        self.environment = enclosing;
+        self.environment
+            .borrow_mut()
+            .define(name.name(), Value::Class(Rc::new(class)));
+
+        Ok(())
+    }
+
+    /// Executes the statements in the given environment, the current one is
+    /// restored afterwards, even if a statement fails or returns.
+    pub fn execute_block(
+        &mut self,
+        statements: &[Stmt],
+        environment: Rc<RefCell<Environment>>,
+    ) -> Result<(), Signal> {
+        let previous = std::mem::replace(&mut self.environment, environment);
+
+        let result = self.execute_all(statements);
+
+        self.environment = previous;
+
+        result
+    }
+
+    // a plain loop rather than `try_for_each`, every frame on the way to the
+    // next call counts against the stack
+    fn execute_all(&mut self, statements: &[Stmt]) -> Result<(), Signal> {
+        for stmt in statements {
+            self.execute(stmt)?;
+        }
+
+        Ok(())
+    }
+
+    /// Same as `execute_block`, for a single expression.
+    pub fn evaluate_in(
+        &mut self,
+        expr: &Expr,
+        environment: Rc<RefCell<Environment>>,
+    ) -> Result<Value, RuntimeError> {
+        let previous = std::mem::replace(&mut self.environment, environment);
+
+        let result = self.evaluate(expr);
+
+        self.environment = previous;
+
+        result
+    }
+
+    pub fn evaluate(&mut self, expr: &Expr) -> Result<Value, RuntimeError> {
+        match expr {
+            Expr::Binary {
+                left,
+                operator,
+                right,
+            } => {
+                let left = self.evaluate(left)?;
+                let right = self.evaluate(right)?;
+
+                binary(&left, operator, &right)
+            }
+            Expr::Unary { operator, right } => self.evaluate_unary(operator, right),
+            Expr::Postfix { left, operator } => {
+                let left = self.evaluate(left)?;
+
+                postfix(&left, operator)
+            }
+            Expr::Literal(literal) => Ok(literal_value(literal)),
+            Expr::Grouping(expr) => self.evaluate(expr),
+            Expr::Super { id, method, .. } => self.super_method(*id, method),
+            Expr::Variable { id, name } | Expr::This { id, keyword: name } => {
+                self.look_up_variable(*id, name)
+            }
+            Expr::Assign { id, name, value } => self.evaluate_assign(*id, name, value),
+            Expr::Logical {
+                left,
+                operator,
+                right,
+            } => {
+                let left = self.evaluate(left)?;
+
+                // the operand itself is the result, not its truthiness
+                let is_short_circuit = match operator.token_type() {
+                    TokenType::Or => left.is_truthy(),
+                    _ => !left.is_truthy(),
+                };
+
+                if is_short_circuit {
+                    return Ok(left);
+                }
+
+                self.evaluate(right)
+            }
+            Expr::Call {
+                callee,
+                paren,
+                arguments,
+            } => self.evaluate_call(callee, paren, arguments),
+            Expr::Interpolation(parts) => self.evaluate_interpolation(parts),
+            Expr::Lambda(declaration) => {
+                let function =
+                    LoxFunction::new(Rc::clone(declaration), Rc::clone(&self.environment), false);
+
+                Ok(Value::Callable(Rc::new(function)))
+            }
+            Expr::Conditional {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                if self.evaluate(condition)?.is_truthy() {
+                    self.evaluate(then_branch)
+                } else {
+                    self.evaluate(else_branch)
+                }
+            }
+            Expr::List { elements, .. } => self.evaluate_list(elements),
+            Expr::Map { brace, entries } => self.evaluate_map(brace, entries),
+            Expr::Index {
+                object,
+                bracket,
+                index,
+            } => self.evaluate_index(object, bracket, index),
+            Expr::SetIndex {
+                object,
+                bracket,
+                index,
+                value,
+                operator,
+            } => self.evaluate_set_index(object, bracket, index, value, operator.as_ref()),
+            Expr::Get { object, name } => self.evaluate_get(object, name),
+            Expr::Set {
+                object,
+                name,
+                value,
+                operator,
+            } => self.evaluate_set(object, name, value, operator.as_ref()),
+        }
+    }
+
+    fn evaluate_interpolation(&mut self, parts: &[Expr]) -> Result<Value, RuntimeError> {
+        let mut string = String::new();
+        for part in parts {
+            string.push_str(&self.evaluate(part)?.to_string());
+        }
+
+        Ok(Value::Str(string))
+    }
+
+    fn evaluate_list(&mut self, elements: &[Expr]) -> Result<Value, RuntimeError> {
+        let elements = elements
+            .iter()
+            .map(|element| self.evaluate(element))
+            .collect::<Result<_, _>>()?;
+
+        Ok(Value::List(Rc::new(RefCell::new(elements))))
+    }
+
+    fn evaluate_unary(&mut self, operator: &Token, right: &Expr) -> Result<Value, RuntimeError> {
+        let right = self.evaluate(right)?;
+
+        match (operator.token_type(), right) {
+            (TokenType::Minus, Value::Int(integer)) => Ok(integer
+                .checked_neg()
+                .map_or(Value::Number(-(integer as f64)), Value::Int)),
+            (TokenType::Minus, Value::Number(number)) => Ok(Value::Number(-number)),
+            (TokenType::Minus, _) => Err(RuntimeError::new(
+                RuntimeErrorType::OperandMustBeNumber,
+                operator.span().line,
+            )),
+            (TokenType::TypeOf, right) => Ok(Value::Str(right.type_name().to_owned())),
+            (_, right) => Ok(Value::Bool(!right.is_truthy())),
+        }
+    }
+
+    fn evaluate_assign(
+        &mut self,
+        id: ExprId,
+        name: &Token,
+        value: &Expr,
+    ) -> Result<Value, RuntimeError> {
+        let value = self.evaluate(value)?;
+
+        match self.locals.get(&id) {
+            Some(distance) => {
+                self.environment
+                    .borrow_mut()
+                    .assign_at(*distance, name, value.clone())?;
+            }
+            None => self.globals.borrow_mut().assign(name, value.clone())?,
+        }
+
+        Ok(value)
+    }
+
+    fn evaluate_call(
+        &mut self,
+        callee: &Expr,
+        paren: &Token,
+        arguments: &[Expr],
+    ) -> Result<Value, RuntimeError> {
+        let callee = match callee {
+            // the getter would run when the property is read and its result
+            // would be called instead
+            Expr::Get { object, name } => match self.evaluate(object)? {
+                Value::Instance(instance) if instance.getter(name.name()).is_some() => {
+                    return Err(RuntimeError::new(
+                        RuntimeErrorType::GetterCalled(name.name().to_owned()),
+                        name.span().line,
+                    ));
+                }
+                object => self.get_property(object, name)?,
+            },
+            callee => self.evaluate(callee)?,
+        };
+
+        let arguments = arguments
+            .iter()
+            .map(|argument| self.evaluate(argument))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let callable: Rc<dyn Callable> = match callee {
+            Value::Callable(callable) => callable,
+            Value::Class(class) => class,
+            _ => {
+                return Err(RuntimeError::new(
+                    RuntimeErrorType::NotCallable,
+                    paren.span().line,
+                ))
+            }
+        };
+
+        self.call(callable, arguments, paren)
+    }
+
+    fn evaluate_map(
+        &mut self,
+        brace: &Token,
+        entries: &[(Expr, Expr)],
+    ) -> Result<Value, RuntimeError> {
+        let mut map = HashMap::new();
+        for (key, value) in entries {
+            let key = MapKey::new(&self.evaluate(key)?, brace)?;
+            map.insert(key, self.evaluate(value)?);
+        }
+
+        Ok(Value::Map(Rc::new(RefCell::new(map))))
+    }
+
+    fn evaluate_index(
+        &mut self,
+        object: &Expr,
+        bracket: &Token,
+        index: &Expr,
+    ) -> Result<Value, RuntimeError> {
+        let object = self.evaluate(object)?;
+        let index = self.evaluate(index)?;
+
+        element(&object, &index, bracket)
+    }
+
+    fn evaluate_set_index(
+        &mut self,
+        object: &Expr,
+        bracket: &Token,
+        index: &Expr,
+        value: &Expr,
+        operator: Option<&Token>,
+    ) -> Result<Value, RuntimeError> {
+        let object = self.evaluate(object)?;
+        let index = self.evaluate(index)?;
+        let value = match operator {
+            Some(operator) => {
+                let current = element(&object, &index, bracket)?;
+                binary(&current, operator, &self.evaluate(value)?)?
+            }
+            None => self.evaluate(value)?,
+        };
+
+        match object {
+            Value::List(list) => {
+                let mut list = list.borrow_mut();
+                let index = list_index(&list, &index, bracket)?;
+
+                list[index] = value.clone();
+            }
+            Value::Map(map) => {
+                let key = MapKey::new(&index, bracket)?;
+
+                map.borrow_mut().insert(key, value.clone());
+            }
+            _ => {
+                return Err(RuntimeError::new(
+                    RuntimeErrorType::NotIndexable,
+                    bracket.span().line,
+                ))
+            }
+        }
+
+        Ok(value)
+    }
+
+    fn evaluate_get(&mut self, object: &Expr, name: &Token) -> Result<Value, RuntimeError> {
+        let object = self.evaluate(object)?;
+
+        self.get_property(object, name)
+    }
+
+    fn get_property(&mut self, object: Value, name: &Token) -> Result<Value, RuntimeError> {
+        match object {
+            Value::Instance(instance) => self.property(&instance, name),
+            Value::Class(class) => match class.find_static_method(name.name()) {
+                Some(method) => Ok(Value::Callable(method)),
+                None => Err(RuntimeError::new(
+                    RuntimeErrorType::UndefinedProperty(name.name().to_owned()),
+                    name.span().line,
+                )),
+            },
+            _ => Err(RuntimeError::new(
+                RuntimeErrorType::OnlyInstancesHaveProperties,
+                name.span().line,
+            )),
+        }
+    }
+
+    fn evaluate_set(
+        &mut self,
+        object: &Expr,
+        name: &Token,
+        value: &Expr,
+        operator: Option<&Token>,
+    ) -> Result<Value, RuntimeError> {
+        let Value::Instance(instance) = self.evaluate(object)? else {
+            return Err(RuntimeError::new(
+                RuntimeErrorType::OnlyInstancesHaveFields,
+                name.span().line,
+            ));
+        };
+
+        let value = match operator {
+            Some(operator) => {
+                let current = self.property(&instance, name)?;
+                binary(&current, operator, &self.evaluate(value)?)?
+            }
+            None => self.evaluate(value)?,
+        };
+        instance.set(name, value.clone());
+
+        Ok(value)
+    }
+
+    /// Reads the property of the instance, running its getter if it has one.
+    fn property(&mut self, instance: &LoxInstance, name: &Token) -> Result<Value, RuntimeError> {
+        match instance.getter(name.name()) {
+            Some(getter) => self.call(Rc::new(getter), Vec::new(), name),
+            None => instance.get(name),
+        }
+    }
+
+    /// Calls the callable after checking the number of arguments and the call
+    /// depth.
+    pub fn call(
+        &mut self,
+        callable: Rc<dyn Callable>,
+        arguments: Vec<Value>,
+        paren: &Token,
+    ) -> Result<Value, RuntimeError> {
+        let (min_arity, arity) = (callable.min_arity(), callable.arity());
+        let too_many = arguments.len() > arity && !callable.is_variadic();
+        if arguments.len() < min_arity || too_many {
+            let t = if callable.is_variadic() {
+                RuntimeErrorType::TooFewArguments {
+                    min: min_arity,
+                    got: arguments.len(),
+                }
+            } else if min_arity == arity {
+                RuntimeErrorType::ArityMismatch {
+                    expected: arity,
+                    got: arguments.len(),
+                }
+            } else {
+                RuntimeErrorType::ArityRangeMismatch {
+                    min: min_arity,
+                    max: arity,
+                    got: arguments.len(),
+                }
+            };
+
+            return Err(RuntimeError::new(t, paren.span().line));
+        }
+
+        if self.call_depth >= self.max_call_depth {
+            return Err(RuntimeError::new(
+                RuntimeErrorType::StackOverflow,
+                paren.span().line,
+            ));
+        }
+
+        self.call_depth += 1;
+        let result = callable.call(self, arguments, paren);
+        self.call_depth -= 1;
+
+        result
+    }
+
+    /// `super` is resolved to the scope around the one that binds `this`.
+    fn super_method(&self, id: ExprId, method: &Token) -> Result<Value, RuntimeError> {
+        let distance = self.locals[&id];
+        let environment = self.environment.borrow();
+
+        let (Some(Value::Class(superclass)), Some(Value::Instance(instance))) = (
+            environment.lookup_at(distance, "super"),
+            environment.lookup_at(distance - 1, "this"),
+        ) else {
+            unreachable!("the resolver binds `super` and `this` in every subclass method")
+        };
+
+        match superclass.find_method(method.name()) {
+            Some(function) => Ok(Value::Callable(Rc::new(function.bind(instance)))),
+            None => Err(RuntimeError::new(
+                RuntimeErrorType::UndefinedProperty(method.name().to_owned()),
+                method.span().line,
+            )),
+        }
+    }
+
+    fn look_up_variable(&self, id: ExprId, name: &Token) -> Result<Value, RuntimeError> {
+        match self.locals.get(&id) {
+            Some(distance) => self.environment.borrow().get_at(*distance, name),
+            None => self.globals.borrow().get(name),
+        }
+    }
+}
+
+/// Logs the statement about to be executed, along with its line if it has
+/// one.
+fn trace_stmt(stmt: &Stmt) {
+    match stmt.line() {
+        Some(line) => debug!("[line {line}] {}", ast_printer::print_stmt(stmt)),
+        None => debug!("{}", ast_printer::print_stmt(stmt)),
+    }
+}
+
+fn trace_value(value: &Value) {
+    debug!("=> {value}");
+}
+
+/// The element of a list or the value of a map at the index, a missing key
+/// reads as `nil` instead of being an error.
+fn element(object: &Value, index: &Value, bracket: &Token) -> Result<Value, RuntimeError> {
+    match object {
+        Value::List(list) => {
+            let list = list.borrow();
+
+            Ok(list[list_index(&list, index, bracket)?].clone())
+        }
+        Value::Map(map) => {
+            let key = MapKey::new(index, bracket)?;
+
+            Ok(map.borrow().get(&key).cloned().unwrap_or(Value::Nil))
+        }
+        _ => Err(RuntimeError::new(
+            RuntimeErrorType::NotIndexable,
+            bracket.span().line,
+        )),
+    }
+}
+
+/// Checks that the index is an integer within the list.
+fn list_index(list: &[Value], index: &Value, bracket: &Token) -> Result<usize, RuntimeError> {
+    let error = |t| Err(RuntimeError::new(t, bracket.span().line));
+
+    let Some(index) = index.as_integer() else {
+        return error(RuntimeErrorType::IndexMustBeInteger);
+    };
+
+    match usize::try_from(index) {
+        Ok(index) if index < list.len() => Ok(index),
+        _ => error(RuntimeErrorType::IndexOutOfRange),
+    }
+}
+
+fn binary(left: &Value, operator: &Token, right: &Value) -> Result<Value, RuntimeError> {
+    let error = |t| Err(RuntimeError::new(t, operator.span().line));
+
+    let value = match operator.token_type() {
+        TokenType::EqualEqual => Value::Bool(left == right),
+        TokenType::BangEqual => Value::Bool(left != right),
+        TokenType::Plus => match (left, right) {
+            (Value::Str(left), Value::Str(right)) => Value::Str(format!("{left}{right}")),
+            _ => match arithmetic(left, &TokenType::Plus, right) {
+                Some(value) => value,
+                None => return error(RuntimeErrorType::OperandsMustBeNumbersOrStrings),
+            },
+        },
+        TokenType::Ampersand
+        | TokenType::Pipe
+        | TokenType::Caret
+        | TokenType::LessLess
+        | TokenType::GreaterGreater => {
+            let (Some(left), Some(right)) = (left.as_integer(), right.as_integer()) else {
+                return error(RuntimeErrorType::OperandsMustBeIntegers);
+            };
+
+            let integer = match operator.token_type() {
+                TokenType::Ampersand => left & right,
+                TokenType::Pipe => left | right,
+                TokenType::Caret => left ^ right,
+                TokenType::LessLess if (0..64).contains(&right) => left << right,
+                TokenType::GreaterGreater if (0..64).contains(&right) => left >> right,
+                _ => return error(RuntimeErrorType::InvalidShift),
+            };
+
+            Value::Int(integer)
+        }
+        TokenType::Slash | TokenType::Percent | TokenType::TildeSlash
+            if left.as_number().is_some() && right.as_number() == Some(0.0) =>
+        {
+            return error(RuntimeErrorType::DivisionByZero)
+        }
+        operator_type => match arithmetic(left, operator_type, right) {
+            Some(value) => value,
+            None => return error(RuntimeErrorType::OperandsMustBeNumbers),
+        },
+    };
+
+    Ok(value)
+}
+
+/// Operations on two integers give an integer, unless the result overflows
+/// or the operator is `/`. The operands are promoted to floats otherwise.
+/// `None` if either operand isn't a number.
+fn arithmetic(left: &Value, operator_type: &TokenType, right: &Value) -> Option<Value> {
+    if let (Value::Int(left), Value::Int(right)) = (left, right) {
+        let integer = match operator_type {
+            TokenType::Plus => left.checked_add(*right),
+            TokenType::Minus => left.checked_sub(*right),
+            TokenType::Star => left.checked_mul(*right),
+            TokenType::Percent => left.checked_rem(*right),
+            TokenType::TildeSlash => floor_division(*left, *right),
+            TokenType::Greater => return Some(Value::Bool(left > right)),
+            TokenType::GreaterEqual => return Some(Value::Bool(left >= right)),
+            TokenType::Less => return Some(Value::Bool(left < right)),
+            TokenType::LessEqual => return Some(Value::Bool(left <= right)),
+            _ => None,
+        };
+
+        if let Some(integer) = integer {
+            return Some(Value::Int(integer));
+        }
+    }
+
+    let (left, right) = (left.as_number()?, right.as_number()?);
+
+    let value = match operator_type {
+        TokenType::Plus => Value::Number(left + right),
+        TokenType::Minus => Value::Number(left - right),
+        TokenType::Star => Value::Number(left * right),
+        TokenType::Slash => Value::Number(left / right),
+        TokenType::Percent => Value::Number(left % right),
+        TokenType::TildeSlash => Value::Number((left / right).floor()),
+        TokenType::Greater => Value::Bool(left > right),
+        TokenType::GreaterEqual => Value::Bool(left >= right),
+        TokenType::Less => Value::Bool(left < right),
+        TokenType::LessEqual => Value::Bool(left <= right),
+        _ => unreachable!("not a binary operator: {operator_type:?}"),
+    };
+
+    Some(value)
+}
+
+fn literal_value(literal: &Literal) -> Value {
+    match literal {
+        Literal::Int(integer) => Value::Int(*integer),
+        Literal::Number(number) => Value::Number(*number),
+        Literal::String(string) => Value::Str(string.clone()),
+        Literal::Bool(bool) => Value::Bool(*bool),
+        Literal::Nil => Value::Nil,
+    }
+}
+
+fn postfix(operand: &Value, operator: &Token) -> Result<Value, RuntimeError> {
+    match operator.token_type() {
+        TokenType::Bang => factorial(operand, operator),
+        operator_type => unreachable!("not a postfix operator: {operator_type:?}"),
+    }
+}
+
+/// Promoted to a float once it overflows, like the other operations on
+/// integers.
+fn factorial(operand: &Value, operator: &Token) -> Result<Value, RuntimeError> {
+    let Some(n) = operand.as_integer().filter(|n| *n >= 0) else {
+        return Err(RuntimeError::new(
+            RuntimeErrorType::OperandMustBeNonNegativeInteger,
+            operator.span().line,
+        ));
+    };
+
+    let mut product = 1_i64;
+    for factor in 2..=n {
+        match product.checked_mul(factor) {
+            Some(next) => product = next,
+            None => {
+                // 171! is already infinite as a float
+                let float = (factor..=n.min(171))
+                    .fold(product as f64, |float, factor| float * factor as f64);
+
+                return Ok(Value::Number(float));
+            }
+        }
+    }
+
+    Ok(Value::Int(product))
+}
+
+/// Division rounded towards negative infinity, like `~/` on floats.
+fn floor_division(left: i64, right: i64) -> Option<i64> {
+    let quotient = left.checked_div(right)?;
+
+    if left % right != 0 && (left < 0) != (right < 0) {
+        Some(quotient - 1)
+    } else {
+        Some(quotient)
+    }
+}
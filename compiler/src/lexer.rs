@@ -1,18 +1,100 @@
-use std::{iter::Peekable, str::Chars};
+use std::{fmt, iter::Peekable, rc::Rc, str::Chars};
+
+/// Byte range of a lexeme in the source, along with the line it starts on.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+}
 
-#[derive(Debug)]
+impl Span {
+    pub fn slice<'a>(&self, source: &'a str) -> &'a str {
+        &source[self.start..self.end]
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Token {
     t: TokenType,
-    line: usize,
+    // the text of the token as it is in the source
+    lexeme: String,
+    span: Span,
+    column: usize,
+    // set by a `//#line` directive, the line of the span is remapped too
+    file: Option<Rc<str>>,
 }
 
 impl Token {
-    pub fn new(t: TokenType, line: usize) -> Self {
-        Self { t, line }
+    /// Tokens that aren't scanned from the source, like the ones the parser
+    /// desugars to, have the lexeme they would be written with.
+    pub fn new(t: TokenType, span: Span, column: usize) -> Self {
+        let lexeme = match &t {
+            TokenType::Identifier(name) => name.clone(),
+            t => t.lexeme().unwrap_or_default().to_owned(),
+        };
+
+        Self::with_lexeme(t, lexeme, span, column)
+    }
+
+    fn with_lexeme(t: TokenType, lexeme: String, span: Span, column: usize) -> Self {
+        Self {
+            t,
+            lexeme,
+            span,
+            column,
+            file: None,
+        }
+    }
+
+    pub fn token_type(&self) -> &TokenType {
+        &self.t
+    }
+
+    pub fn lexeme(&self) -> &str {
+        &self.lexeme
+    }
+
+    pub fn line(&self) -> usize {
+        self.span.line
+    }
+
+    /// Name of an identifier token, `this` and `super` are named too as
+    /// methods bind them like variables. Empty for every other token type.
+    pub fn name(&self) -> &str {
+        match &self.t {
+            TokenType::Identifier(name) => name,
+            TokenType::This => "this",
+            TokenType::Super => "super",
+            _ => "",
+        }
+    }
+
+    pub fn span(&self) -> &Span {
+        &self.span
+    }
+
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
+    /// Shared by all the tokens after the same `//#line` directive, so that
+    /// the errors found at them can keep it cheaply too.
+    pub fn file(&self) -> Option<&Rc<str>> {
+        self.file.as_ref()
     }
 }
 
-#[derive(Debug)]
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{} {:?}", self.span.line, self.column, self.t)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[rustfmt::skip]
 pub enum TokenType {
     None,
@@ -20,60 +102,296 @@ pub enum TokenType {
     // Single-character tokens.
     LeftParen, RightParen,
     LeftBrace, RightBrace,
-    Comma, Dot, Semicolon, Slash, Star,
+    LeftBracket, RightBracket,
+    Comma, Dot, Semicolon, Slash, Star, Percent,
+    DotDotDot,
+    Ampersand, Pipe, Caret,
+    Question, Colon,
     Minus, Plus,     
     // One or two character tokens.
     Bang, BangEqual, 
+    PlusEqual, MinusEqual, StarEqual, SlashEqual,
+    PlusPlus, MinusMinus,
     Equal,EqualEqual,
-    Greater, GreaterEqual,
-    Less, LessEqual,
+    Greater, GreaterEqual, GreaterGreater,
+    Less, LessEqual, LessLess,
+    TildeSlash,
     // Literals.
     Identifier(String),
     String(String),
+    // string with `${expr}` placeholders
+    Interpolation(Vec<StringPart>),
+    // numbers without a fractional part or an exponent
+    Int(i64),
     Number(f64),
     // Keywords.
     If, Else, And, Or, False, True,
     Class, Super, This,
-    Var, For,While,
+    Var, For,While, Do,
     Fun, Print,
     Return,Nil,
+    Break, Continue,
+    Assert,
+    Switch, Case, Default,
+    In, Static,
+    Try, Catch, Throw,
+    TypeOf,
+    Include,
     Eof,
 }
 
+/// Piece of an interpolated string, the tokens of an expression end with
+/// `Eof` so that they can be parsed on their own.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum StringPart {
+    Literal(String),
+    Expression(Vec<Token>),
+}
+
+impl TokenType {
+    /// Compares only the kind of the tokens, ignoring the literal values, so
+    /// `Number(1.0)` is the same kind as `Number(2.0)`.
+    pub fn same_kind(&self, other: &TokenType) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+    }
+
+    /// How keywords and punctuation are spelled in the source, the tokens
+    /// that carry a value or aren't written at all have no single spelling.
+    pub fn lexeme(&self) -> Option<&'static str> {
+        let lexeme = match self {
+            TokenType::LeftParen => "(",
+            TokenType::RightParen => ")",
+            TokenType::LeftBrace => "{",
+            TokenType::RightBrace => "}",
+            TokenType::LeftBracket => "[",
+            TokenType::RightBracket => "]",
+            TokenType::Comma => ",",
+            TokenType::Dot => ".",
+            TokenType::Semicolon => ";",
+            TokenType::Slash => "/",
+            TokenType::Star => "*",
+            TokenType::Percent => "%",
+            TokenType::DotDotDot => "...",
+            TokenType::Ampersand => "&",
+            TokenType::Pipe => "|",
+            TokenType::Caret => "^",
+            TokenType::TildeSlash => "~/",
+            TokenType::Question => "?",
+            TokenType::Colon => ":",
+            TokenType::Minus => "-",
+            TokenType::Plus => "+",
+            TokenType::Bang => "!",
+            TokenType::BangEqual => "!=",
+            TokenType::PlusEqual => "+=",
+            TokenType::MinusEqual => "-=",
+            TokenType::StarEqual => "*=",
+            TokenType::SlashEqual => "/=",
+            TokenType::PlusPlus => "++",
+            TokenType::MinusMinus => "--",
+            TokenType::Equal => "=",
+            TokenType::EqualEqual => "==",
+            TokenType::Greater => ">",
+            TokenType::GreaterEqual => ">=",
+            TokenType::GreaterGreater => ">>",
+            TokenType::Less => "<",
+            TokenType::LessEqual => "<=",
+            TokenType::LessLess => "<<",
+            TokenType::If => "if",
+            TokenType::Else => "else",
+            TokenType::And => "and",
+            TokenType::Or => "or",
+            TokenType::False => "false",
+            TokenType::True => "true",
+            TokenType::Class => "class",
+            TokenType::Super => "super",
+            TokenType::This => "this",
+            TokenType::Var => "var",
+            TokenType::For => "for",
+            TokenType::While => "while",
+            TokenType::Do => "do",
+            TokenType::Fun => "fun",
+            TokenType::Print => "print",
+            TokenType::Return => "return",
+            TokenType::Nil => "nil",
+            TokenType::Break => "break",
+            TokenType::Continue => "continue",
+            TokenType::Assert => "assert",
+            TokenType::Switch => "switch",
+            TokenType::Case => "case",
+            TokenType::Default => "default",
+            TokenType::In => "in",
+            TokenType::Static => "static",
+            TokenType::Try => "try",
+            TokenType::Catch => "catch",
+            TokenType::Throw => "throw",
+            TokenType::TypeOf => "typeof",
+            TokenType::Include => "include",
+            TokenType::None
+            | TokenType::Comment
+            | TokenType::Identifier(_)
+            | TokenType::String(_)
+            | TokenType::Interpolation(_)
+            | TokenType::Int(_)
+            | TokenType::Number(_)
+            | TokenType::Eof => return None,
+        };
+
+        Some(lexeme)
+    }
+}
+
 #[derive(Debug)]
 pub enum LexingErrorType {
     UnexpectedCharacter,
     UnterminatedString,
-    NumberPrasingError,
+    UnterminatedBlockComment,
+    InvalidEscapeSequence,
+    NumberParsingError,
+    UnterminatedInterpolation,
+    InvalidLineDirective,
 }
 
 #[derive(Debug)]
 pub struct LexingError {
     t: LexingErrorType,
-    line: usize,
+    // position of the lexeme the error was found at
+    span: Span,
+    column: usize,
+    file: Option<Rc<str>>,
 }
 
 impl LexingError {
-    pub fn new(t: LexingErrorType, line: usize) -> Self {
-        Self { t, line }
+    pub fn new(t: LexingErrorType, span: Span, column: usize) -> Self {
+        Self {
+            t,
+            span,
+            column,
+            file: None,
+        }
+    }
+
+    pub fn span(&self) -> &Span {
+        &self.span
+    }
+
+    pub fn line(&self) -> usize {
+        self.span.line
+    }
+
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
+    pub fn file(&self) -> Option<&str> {
+        self.file.as_deref()
+    }
+
+    pub fn error_type(&self) -> &LexingErrorType {
+        &self.t
     }
 }
 
-#[inline]
-fn match_next_lexeme(
-    source: &mut Peekable<Chars>,
-    expected: char,
-    then: TokenType,
-    otherwise: TokenType,
-) -> TokenType {
-    if source.next_if_eq(&expected).is_some() {
-        source.next();
-        then
-    } else {
-        otherwise
+impl fmt::Display for LexingErrorType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = match self {
+            LexingErrorType::UnexpectedCharacter => "Unexpected character.",
+            LexingErrorType::UnterminatedString => "Unterminated string.",
+            LexingErrorType::UnterminatedBlockComment => "Unterminated block comment.",
+            LexingErrorType::InvalidEscapeSequence => "Invalid escape sequence.",
+            LexingErrorType::NumberParsingError => "Invalid number literal.",
+            LexingErrorType::UnterminatedInterpolation => "Unterminated interpolation.",
+            LexingErrorType::InvalidLineDirective => "Invalid line directive.",
+        };
+
+        write!(f, "{message}")
     }
 }
 
+impl fmt::Display for LexingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[line {}] Error: {}", self.span.line, self.t)
+    }
+}
+
+impl std::error::Error for LexingError {}
+
+/// Wraps the source characters and keeps track of the position of the next
+/// character, both line and column are 1-based, the offset is in bytes.
+struct Cursor<'a> {
+    source: &'a str,
+    chars: Peekable<Chars<'a>>,
+    offset: usize,
+    line: usize,
+    column: usize,
+    // file named by the last `//#line` directive
+    file: Option<Rc<str>>,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(source: &'a str) -> Self {
+        Self {
+            source,
+            chars: source.chars().peekable(),
+            offset: 0,
+            line: 1,
+            column: 1,
+            file: None,
+        }
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let char = self.chars.next()?;
+        self.offset += char.len_utf8();
+
+        if char == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+
+        Some(char)
+    }
+
+    /// Span from `start` on `line` up to the next character.
+    fn span_from(&self, start: usize, line: usize) -> Span {
+        Span {
+            start,
+            end: self.offset,
+            line,
+        }
+    }
+
+    /// The next char without consuming it, `None` only at the end of the
+    /// input, so a NUL in the source is just another char.
+    fn peek(&mut self) -> Option<char> {
+        self.chars.peek().copied()
+    }
+
+    fn peek_next(&self) -> Option<char> {
+        let mut chars = self.chars.clone();
+        chars.next();
+        chars.next()
+    }
+
+    fn next_if(&mut self, func: impl FnOnce(&char) -> bool) -> Option<char> {
+        if func(&self.peek()?) {
+            self.next()
+        } else {
+            None
+        }
+    }
+
+    /// Consumes the next char only if it is `expected`.
+    fn match_char(&mut self, expected: char) -> bool {
+        self.next_if(|char| *char == expected).is_some()
+    }
+}
+
+/// The keyword the identifier spells, if any. The table is a `match`, so
+/// nothing is built when a lexer is created and the keyword token types
+/// carry no data to clone.
 fn which_keyword(identifier: &str) -> Option<TokenType> {
     let identifier = match identifier {
         "if" => TokenType::If,
@@ -88,173 +406,529 @@ fn which_keyword(identifier: &str) -> Option<TokenType> {
         "var" => TokenType::Var,
         "for" => TokenType::For,
         "while" => TokenType::While,
+        "do" => TokenType::Do,
         "fun" => TokenType::Fun,
         "print" => TokenType::Print,
         "return" => TokenType::Return,
         "nil" => TokenType::Nil,
+        "break" => TokenType::Break,
+        "continue" => TokenType::Continue,
+        "assert" => TokenType::Assert,
+        "switch" => TokenType::Switch,
+        "case" => TokenType::Case,
+        "default" => TokenType::Default,
+        "in" => TokenType::In,
+        "static" => TokenType::Static,
+        "try" => TokenType::Try,
+        "catch" => TokenType::Catch,
+        "throw" => TokenType::Throw,
+        "typeof" => TokenType::TypeOf,
+        "include" => TokenType::Include,
         _ => return None,
     };
 
     Some(identifier)
 }
 
+/// Scans the rest of a number literal, supports decimals with an optional
+/// fractional part and exponent, as well as `0x` hexadecimal and `0b` binary
+/// integers. Decimal digits can be grouped with `_` separators.
+/// Literals too large for an `i64` are scanned as floats.
+fn number(source: &mut Cursor, first_digit: char) -> Option<TokenType> {
+    if first_digit == '0' {
+        let radix = match source.peek() {
+            Some('x' | 'X') => Some(16),
+            Some('b' | 'B') => Some(2),
+            _ => None,
+        };
+
+        if let Some(radix) = radix {
+            source.next();
+
+            // take every alphanumeric char so that `0b12` is rejected as a whole
+            let mut digits = String::new();
+            while let Some(digit) = source.next_if(char::is_ascii_alphanumeric) {
+                digits.push(digit);
+            }
+
+            return u64::from_str_radix(&digits, radix).ok().map(|number| {
+                i64::try_from(number).map_or(TokenType::Number(number as f64), TokenType::Int)
+            });
+        }
+    }
+
+    let mut number_value = String::from(first_digit);
+    let mut is_valid = decimal_digits(source, &mut number_value);
+    let mut is_float = false;
+
+    // the dot belongs to the number only if it is followed by the fractional part
+    if source.peek() == Some('.') && source.peek_next().is_some_and(|char| char.is_ascii_digit()) {
+        source.next();
+        number_value.push('.');
+        is_float = true;
+
+        is_valid &= decimal_digits(source, &mut number_value);
+    }
+
+    if let Some(marker) = source.next_if(|char| matches!(char, 'e' | 'E')) {
+        number_value.push(marker);
+        is_float = true;
+
+        if let Some(sign) = source.next_if(|char| matches!(char, '+' | '-')) {
+            number_value.push(sign);
+        }
+
+        is_valid &= decimal_digits(source, &mut number_value);
+    }
+
+    if !is_valid {
+        return None;
+    }
+
+    if !is_float {
+        if let Ok(integer) = number_value.parse() {
+            return Some(TokenType::Int(integer));
+        }
+    }
+
+    number_value.parse().ok().map(TokenType::Number)
+}
+
+/// Pushes a run of decimal digits, which may be separated by underscores,
+/// returns `false` if a separator isn't placed between two digits.
+fn decimal_digits(source: &mut Cursor, number_value: &mut String) -> bool {
+    let mut is_valid = true;
+    while let Some(digit) = source.next_if(|char| char.is_ascii_digit() || *char == '_') {
+        if digit == '_' {
+            let follows_digit = number_value.ends_with(|char: char| char.is_ascii_digit());
+            is_valid &= follows_digit && source.peek().is_some_and(|char| char.is_ascii_digit());
+        } else {
+            number_value.push(digit);
+        }
+    }
+
+    is_valid
+}
+
+fn escape_sequence(escaped: char) -> Option<char> {
+    let char = match escaped {
+        'n' => '\n',
+        't' => '\t',
+        'r' => '\r',
+        '\\' => '\\',
+        '"' => '"',
+        '$' => '$',
+        '0' => '\0',
+        _ => return None,
+    };
+
+    Some(char)
+}
+
+/// Parses what follows `#line` in a line directive, a line number greater
+/// than zero and optionally a file name in double quotes.
+fn line_directive(directive: &str) -> Option<(usize, Option<&str>)> {
+    let directive = directive.strip_prefix(char::is_whitespace)?.trim();
+
+    let (line, file) = match directive.split_once(char::is_whitespace) {
+        Some((line, file)) => (line, Some(file.trim_start())),
+        None => (directive, None),
+    };
+
+    let line = line.parse().ok().filter(|line| *line > 0)?;
+
+    let file = match file {
+        Some(file) => Some(file.strip_prefix('"')?.strip_suffix('"')?),
+        None => None,
+    };
+
+    Some((line, file))
+}
+
+/// Scans the tokens of an interpolated expression, up to the `}` that closes
+/// the `${` that has just been consumed.
+fn interpolation(source: &mut Cursor) -> Result<Vec<Token>, LexingError> {
+    // the `${` is reported, it is two characters back
+    let (start, line, column) = (source.offset - 2, source.line, source.column - 2);
+    let mut tokens = Vec::new();
+    let mut depth = 0;
+
+    loop {
+        let token = match scan_token(source) {
+            Some(token) => token?,
+            None => {
+                return Err(LexingError::new(
+                    LexingErrorType::UnterminatedInterpolation,
+                    Span {
+                        start,
+                        end: start + 2,
+                        line,
+                    },
+                    column,
+                ))
+            }
+        };
+
+        match token.token_type() {
+            TokenType::None | TokenType::Comment => continue,
+            TokenType::LeftBrace => depth += 1,
+            TokenType::RightBrace if depth == 0 => {
+                tokens.push(Token::new(TokenType::Eof, token.span, token.column));
+
+                return Ok(tokens);
+            }
+            TokenType::RightBrace => depth -= 1,
+            _ => {}
+        }
+
+        tokens.push(Token {
+            file: source.file.clone(),
+            ..token
+        });
+    }
+}
+
 #[allow(clippy::too_many_lines)]
-fn scan_token(
-    source: &mut Peekable<Chars>,
-    line: &mut usize,
-) -> Option<Result<(Token, usize), LexingError>> {
+fn scan_token(source: &mut Cursor) -> Option<Result<Token, LexingError>> {
+    let (start, line, column) = (source.offset, source.line, source.column);
+    // errors about the whole lexeme scanned so far
+    let error = |t, source: &Cursor| {
+        Some(Err(LexingError::new(
+            t,
+            source.span_from(start, line),
+            column,
+        )))
+    };
+
     let token = if let Some(char) = source.next() {
         match char {
             '(' => TokenType::LeftParen,
             ')' => TokenType::RightParen,
             '{' => TokenType::LeftBrace,
             '}' => TokenType::RightBrace,
+            '[' => TokenType::LeftBracket,
+            ']' => TokenType::RightBracket,
             ',' => TokenType::Comma,
+            '.' if source.peek() == Some('.') && source.peek_next() == Some('.') => {
+                source.next();
+                source.next();
+
+                TokenType::DotDotDot
+            }
             '.' => TokenType::Dot,
+            '-' if source.match_char('-') => TokenType::MinusMinus,
+            '-' if source.match_char('=') => TokenType::MinusEqual,
             '-' => TokenType::Minus,
+            '+' if source.match_char('+') => TokenType::PlusPlus,
+            '+' if source.match_char('=') => TokenType::PlusEqual,
             '+' => TokenType::Plus,
             ';' => TokenType::Semicolon,
+            '*' if source.match_char('=') => TokenType::StarEqual,
             '*' => TokenType::Star,
-            '!' => match_next_lexeme(source, '=', TokenType::BangEqual, TokenType::Bang),
-            '=' => match_next_lexeme(source, '=', TokenType::EqualEqual, TokenType::Equal),
-            '<' => match_next_lexeme(source, '=', TokenType::LessEqual, TokenType::Less),
-            '>' => match_next_lexeme(source, '=', TokenType::GreaterEqual, TokenType::Greater),
+            '%' => TokenType::Percent,
+            '&' => TokenType::Ampersand,
+            '|' => TokenType::Pipe,
+            '^' => TokenType::Caret,
+            '~' if source.match_char('/') => TokenType::TildeSlash,
+            '?' => TokenType::Question,
+            ':' => TokenType::Colon,
+            '!' if source.match_char('=') => TokenType::BangEqual,
+            '!' => TokenType::Bang,
+            '=' if source.match_char('=') => TokenType::EqualEqual,
+            '=' => TokenType::Equal,
+            '<' if source.match_char('=') => TokenType::LessEqual,
+            '<' if source.match_char('<') => TokenType::LessLess,
+            '<' => TokenType::Less,
+            '>' if source.match_char('=') => TokenType::GreaterEqual,
+            '>' if source.match_char('>') => TokenType::GreaterGreater,
+            '>' => TokenType::Greater,
             '/' => {
-                let token = match_next_lexeme(source, '/', TokenType::Comment, TokenType::Slash);
-
-                if let TokenType::Comment = token {
+                if source.match_char('/') {
                     // consume until end of the line
-                    while let Some(next_char) = source.peek() {
-                        let next_char = *next_char;
-                        source.next();
+                    let mut comment = String::new();
+                    while let Some(next_char) = source.next_if(|next_char| *next_char != '\n') {
+                        comment.push(next_char);
+                    }
 
-                        if next_char == '\n' {
-                            break;
+                    // `//#line N "file"` at the start of a line gives the
+                    // location of the next line, for generated source, while
+                    // comments like `//#lines` are left alone
+                    let directive = comment.strip_prefix("#line").filter(|directive| {
+                        column == 1 && directive.chars().next().is_none_or(char::is_whitespace)
+                    });
+                    if let Some(directive) = directive {
+                        let Some((directive_line, file)) = line_directive(directive) else {
+                            return error(LexingErrorType::InvalidLineDirective, source);
+                        };
+
+                        // the newline that ends the directive moves to the line
+                        source.line = directive_line - 1;
+                        if let Some(file) = file {
+                            source.file = Some(file.into());
                         }
                     }
 
                     TokenType::Comment
+                } else if source.match_char('*') {
+                    // block comments can be nested, so only the matching `*/` ends it
+                    let mut depth = 1;
+                    while depth > 0 {
+                        match source.next() {
+                            Some('/') if source.match_char('*') => depth += 1,
+                            Some('*') if source.match_char('/') => depth -= 1,
+                            Some(_) => {}
+                            None => {
+                                let span = Span {
+                                    start,
+                                    end: start + 2,
+                                    line,
+                                };
+
+                                return Some(Err(LexingError::new(
+                                    LexingErrorType::UnterminatedBlockComment,
+                                    span,
+                                    column,
+                                )));
+                            }
+                        }
+                    }
+
+                    TokenType::Comment
+                } else if source.match_char('=') {
+                    TokenType::SlashEqual
                 } else {
                     TokenType::Slash
                 }
             }
-            ' ' | '\r' | '\t' => TokenType::None,
-            '\n' => {
-                *line += 1;
-                TokenType::None
-            }
+            ' ' | '\r' | '\t' | '\n' => TokenType::None,
             '"' => {
                 let mut found_termination = false;
+                // position of the first invalid escape, the rest aren't reported
+                let mut invalid_escape = None;
                 let mut string_value = String::new();
-                while let Some(next_char) = source.peek() {
-                    let next_char = *next_char;
-
-                    if next_char == '"' {
-                        found_termination = true;
-                        source.next();
-                        break;
-                    }
-
-                    string_value.push(next_char);
-
-                    source.next();
-
-                    if next_char == '\n' {
-                        *line += 1;
+                let mut parts = Vec::new();
+                while let Some(next_char) = source.next() {
+                    match next_char {
+                        '"' => {
+                            found_termination = true;
+                            break;
+                        }
+                        '$' if source.match_char('{') => {
+                            let tokens = match interpolation(source) {
+                                Ok(tokens) => tokens,
+                                Err(error) => return Some(Err(error)),
+                            };
+
+                            parts.push(StringPart::Literal(std::mem::take(&mut string_value)));
+                            parts.push(StringPart::Expression(tokens));
+                        }
+                        '\\' => {
+                            let (escape_start, escape_line, escape_column) =
+                                (source.offset - 1, source.line, source.column - 1);
+
+                            // an invalid escape doesn't stop the scan, so the rest
+                            // of the string isn't lexed as code
+                            match source.next().and_then(escape_sequence) {
+                                Some(escaped_char) => string_value.push(escaped_char),
+                                None => {
+                                    let span = source.span_from(escape_start, escape_line);
+                                    invalid_escape.get_or_insert((span, escape_column));
+                                }
+                            }
+                        }
+                        _ => string_value.push(next_char),
                     }
                 }
 
+                // reported at the opening quote, the end of the input tells nothing
                 if !found_termination {
+                    let span = Span {
+                        start,
+                        end: start + 1,
+                        line,
+                    };
+
                     return Some(Err(LexingError::new(
                         LexingErrorType::UnterminatedString,
-                        *line,
+                        span,
+                        column,
                     )));
                 }
 
-                TokenType::String(string_value)
-            }
-            '0'..='9' => {
-                let mut number_value = String::new();
-                while let Some(next_char) = source.peek() {
-                    let next_char = *next_char;
-                    source.next();
-
-                    if next_char.is_ascii_digit() {
-                        number_value.push(next_char);
-                    } else if next_char == '.' {
-                        number_value.push(next_char);
-
-                        while next_char.is_ascii_digit() {
-                            number_value.push(next_char);
-                        }
-
-                        break;
-                    }
+                if let Some((span, column)) = invalid_escape {
+                    return Some(Err(LexingError::new(
+                        LexingErrorType::InvalidEscapeSequence,
+                        span,
+                        column,
+                    )));
                 }
 
-                if let Ok(number) = number_value.parse::<f64>() {
-                    TokenType::Number(number)
+                if parts.is_empty() {
+                    TokenType::String(string_value)
                 } else {
-                    return Some(Err(LexingError::new(
-                        LexingErrorType::NumberPrasingError,
-                        *line,
-                    )));
+                    parts.push(StringPart::Literal(string_value));
+
+                    TokenType::Interpolation(parts)
                 }
             }
-            'a'..='z' | 'A'..='Z' | '_' => {
-                let mut identifier = String::from(char);
-
-                while let Some(next_char) = source.peek() {
-                    if next_char.is_alphanumeric() || *next_char == '_' {
-                        identifier.push(*next_char);
-                        source.next();
-                    } else {
-                        break;
-                    }
-                }
-
-                if let Some(keyword) = which_keyword(&identifier) {
-                    keyword
+            '0'..='9' => {
+                if let Some(number) = number(source, char) {
+                    number
                 } else {
-                    TokenType::Identifier(identifier)
+                    return error(LexingErrorType::NumberParsingError, source);
                 }
             }
-            _ => {
-                return Some(Err(LexingError::new(
-                    LexingErrorType::UnexpectedCharacter,
-                    *line,
-                )))
+            // identifiers can be in any script, not only in ASCII
+            char if char.is_alphabetic() || char == '_' => {
+                while source
+                    .next_if(|char| char.is_alphanumeric() || *char == '_')
+                    .is_some()
+                {}
+
+                // sliced from the source, so keywords allocate nothing
+                let identifier = &source.source[start..source.offset];
+
+                which_keyword(identifier)
+                    .unwrap_or_else(|| TokenType::Identifier(identifier.to_owned()))
             }
+            _ => return error(LexingErrorType::UnexpectedCharacter, source),
         }
     } else {
         return None;
     };
 
-    Some(Ok((Token::new(token, *line), *line)))
+    let span = source.span_from(start, line);
+    let lexeme = span.slice(source.source).to_owned();
+
+    Some(Ok(Token::with_lexeme(token, lexeme, span, column)))
+}
+
+/// Lazily scans the source, yielding one token or error at a time and `Eof`
+/// exactly once at the end.
+pub struct Lexer<'a> {
+    source: Cursor<'a>,
+    is_finished: bool,
 }
 
-pub fn scan_tokens(source: &str) -> Result<Vec<Token>, Vec<LexingError>> {
-    let mut source = source.chars().peekable();
-    let mut line = 0;
+impl<'a> Lexer<'a> {
+    pub fn new(source: &'a str) -> Self {
+        Self {
+            source: Cursor::new(source),
+            is_finished: false,
+        }
+    }
+
+    /// Scans the rest of the source and serializes the tokens with serde,
+    /// they deserialize back to the same tokens. Unlike `tokens_json` that
+    /// `--emit=json` prints, literal values and the file of a `//#line`
+    /// directive are kept. The errors are returned instead if there are any.
+    #[cfg(feature = "serde")]
+    pub fn tokens_json(self) -> Result<String, Vec<LexingError>> {
+        let (tokens, errors): (Vec<_>, Vec<_>) = self.partition(Result::is_ok);
+
+        if !errors.is_empty() {
+            return Err(errors.into_iter().filter_map(Result::err).collect());
+        }
 
+        let tokens = tokens
+            .into_iter()
+            .filter_map(Result::ok)
+            .collect::<Vec<_>>();
+
+        Ok(serde_json::to_string(&tokens).expect("tokens should serialize to JSON"))
+    }
+}
+
+impl Iterator for Lexer<'_> {
+    type Item = Result<Token, LexingError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.is_finished {
+            return None;
+        }
+
+        while let Some(lexing_result) = scan_token(&mut self.source) {
+            let file = self.source.file.clone();
+
+            match lexing_result {
+                // whitespaces and comments carry no meaning for the parser
+                Ok(token) if matches!(token.t, TokenType::None | TokenType::Comment) => {}
+                Ok(token) => return Some(Ok(Token { file, ..token })),
+                Err(error) => return Some(Err(LexingError { file, ..error })),
+            }
+        }
+
+        self.is_finished = true;
+
+        let span = Span {
+            start: self.source.offset,
+            end: self.source.offset,
+            line: self.source.line,
+        };
+
+        Some(Ok(Token {
+            file: self.source.file.clone(),
+            ..Token::new(TokenType::Eof, span, self.source.column)
+        }))
+    }
+}
+
+/// Scans the whole source, the returned tokens are always terminated with
+/// `Eof`, even if some of the lexemes couldn't be recognized.
+pub fn scan_tokens(source: &str) -> (Vec<Token>, Vec<LexingError>) {
     let mut tokens = Vec::new();
     let mut errors = Vec::new();
 
-    while let Some(lixing_result) = scan_token(&mut source, &mut line) {
-        match lixing_result {
-            Ok((token, new_line)) => {
-                tokens.push(token);
-                line = new_line;
-            }
+    for lexing_result in Lexer::new(source) {
+        match lexing_result {
+            Ok(token) => tokens.push(token),
             Err(lexing_error) => errors.push(lexing_error),
         }
     }
 
-    tokens.push(Token::new(TokenType::Eof, line));
+    (tokens, errors)
+}
+
+/// Renders the tokens as a JSON array, for tools that consume the token
+/// stream. Each token has its type, lexeme and position.
+pub fn tokens_json(tokens: &[Token]) -> String {
+    let tokens = tokens
+        .iter()
+        .map(|token| {
+            // the name of the variant, without its literal value
+            let token_type = format!("{:?}", token.t);
+            let token_type = token_type.split('(').next().unwrap_or_default();
+
+            format!(
+                "  {{\"type\": \"{token_type}\", \"lexeme\": {}, \"line\": {}, \"column\": {}, \"start\": {}, \"end\": {}}}",
+                json_string(&token.lexeme),
+                token.span.line,
+                token.column,
+                token.span.start,
+                token.span.end,
+            )
+        })
+        .collect::<Vec<_>>();
+
+    format!("[\n{}\n]", tokens.join(",\n"))
+}
+
+fn json_string(string: &str) -> String {
+    let mut json = String::from('"');
 
-    if !errors.is_empty() {
-        return Err(errors);
+    for char in string.chars() {
+        match char {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            char if char.is_control() => json.push_str(&format!("\\u{:04x}", char as u32)),
+            char => json.push(char),
+        }
     }
 
-    Ok(tokens)
+    json.push('"');
+
+    json
 }
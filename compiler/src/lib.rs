@@ -0,0 +1,27 @@
+//! Tree-walking interpreter for the Lox language, the `rlox` binary is a thin
+//! command line wrapper around [`lox::Lox`].
+
+pub mod ast_dot;
+pub mod ast_printer;
+pub mod callable;
+pub mod class;
+pub mod environment;
+pub mod error;
+pub mod fold;
+pub mod interpreter;
+pub mod lexer;
+pub mod lox;
+mod natives;
+pub mod parser;
+pub mod resolver;
+
+use crate::{
+    error::LoxError,
+    lox::{Lox, Mode},
+};
+
+/// Runs the program, its output goes to stdout and its errors are reported
+/// to stderr as well as returned.
+pub fn run_source(source: &str) -> Result<(), Vec<LoxError>> {
+    Lox::new(Mode::Run).run(source, None)
+}
@@ -1,56 +1,614 @@
 use std::{
-    fs,
-    io::{self, Error, Write},
+    env,
+    fmt::Display,
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, IsTerminal, Write},
+    mem, panic,
+    path::{Path, PathBuf},
     process::exit,
+    thread,
+    time::{Duration, Instant},
 };
 
-use crate::lexer;
+use log::warn;
+
+use crate::{
+    ast_dot, ast_printer,
+    error::LoxError,
+    fold,
+    interpreter::Interpreter,
+    lexer::{self, Span, TokenType},
+    parser::{ParseError, ParseErrorType, Parser, Stmt},
+    resolver::Resolver,
+};
+
+/// Name the errors of the lines entered in the promt are reported in.
+const REPL_FILE: &str = "<repl>";
+
+/// Stack of the thread the programs run on. A Lox call takes a few Rust
+/// frames, which are several times larger in debug builds, this fits the
+/// default call depth limit of the interpreter in either build with room to
+/// spare for deeply nested function bodies. Only the pages in use are backed
+/// by memory.
+const STACK_SIZE: usize = 256 * 1024 * 1024;
+
+/// What `Lox` does with the source it is given.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Mode {
+    #[default]
+    Run,
+    // prints the scanned tokens instead of running them
+    Tokens,
+    // prints the parsed program as s-expressions instead of running it
+    Ast,
+    // prints the scanned tokens as JSON
+    Json,
+    // prints the parsed program as a Graphviz graph instead of running it
+    Dot,
+    // only reports the errors that can be found without running the program
+    Check,
+}
 
-#[derive(Default)]
 pub struct Lox {
-    had_error: bool,
+    mode: Mode,
+    // errors are colored only on a terminal, unless `NO_COLOR` is set
+    use_color: bool,
+    // where the errors and the durations of the phases are reported
+    error_output: Box<dyn Write + Send>,
+    // lines entered in the promt, including the ones of earlier sessions
+    history: Vec<String>,
+    // file the history is loaded from and saved to, if any
+    history_path: Option<PathBuf>,
+    // open while the promt runs, every line is appended as it is entered
+    history_file: Option<File>,
+    // kept across runs, so that the lines of the promt see the definitions
+    // of the earlier ones
+    interpreter: Interpreter,
+    // whether the durations of the phases are reported after every run
+    time: bool,
+    // durations of the phases of the current run, in the order they ran
+    timings: Vec<(&'static str, Duration)>,
 }
 
 impl Lox {
-    fn run(&mut self, source: &str) {
-        let tokens = lexer::scan_tokens(source);
+    pub fn new(mode: Mode) -> Self {
+        Self::with_output(mode, Box::new(io::stdout()))
+    }
+
+    /// Same as `new`, but the output of the programs, the dumps of the
+    /// tokens and the syntax tree and the output of the promt are written to
+    /// `output` instead of stdout. Errors are still reported to stderr,
+    /// unless `set_error_output` says otherwise.
+    /// The output is sent along to the thread the programs run on.
+    pub fn with_output(mode: Mode, output: Box<dyn Write + Send>) -> Self {
+        Self {
+            mode,
+            use_color: io::stderr().is_terminal() && env::var_os("NO_COLOR").is_none(),
+            error_output: Box::new(io::stderr()),
+            history: Vec::new(),
+            history_path: env::var_os("HOME").map(|home| PathBuf::from(home).join(".rlox_history")),
+            history_file: None,
+            interpreter: Interpreter::new(output),
+            time: false,
+            timings: Vec::new(),
+        }
+    }
+
+    /// Reports the errors and the durations of the phases to `error_output`
+    /// instead of stderr. The errors written to it aren't colored, as it
+    /// isn't known to be a terminal, unless `set_color` is called after.
+    pub fn set_error_output(&mut self, error_output: Box<dyn Write + Send>) {
+        self.error_output = error_output;
+        self.use_color = false;
+    }
+
+    /// Colors the errors with ANSI escapes or leaves them plain, overriding
+    /// what was detected from stderr and `NO_COLOR`.
+    pub fn set_color(&mut self, use_color: bool) {
+        self.use_color = use_color;
+    }
+
+    /// Reports how long lexing, parsing, resolving and interpreting took
+    /// along with the errors after every run, one `phase=lex ms=1.234` line
+    /// per phase that ran.
+    pub fn set_time(&mut self, time: bool) {
+        self.time = time;
+    }
+
+    /// Loads the history of the promt from `history_path` and saves the lines
+    /// entered to it, instead of `$HOME/.rlox_history`. With `None`, the
+    /// history only lasts for the session.
+    pub fn set_history(&mut self, history_path: Option<PathBuf>) {
+        self.history_path = history_path;
+    }
+
+    /// See `Interpreter::set_trace`.
+    pub fn set_trace(&mut self, trace: bool) {
+        self.interpreter.set_trace(trace);
+    }
 
-        match tokens {
-            Ok(tokens) => {
-                dbg!(tokens);
+    /// See `Interpreter::set_max_call_depth`.
+    pub fn set_max_call_depth(&mut self, max_call_depth: usize) {
+        self.interpreter.set_max_call_depth(max_call_depth);
+    }
+
+    /// See `Interpreter::set_precision`, the values of the expressions
+    /// entered in the promt are rounded the same way.
+    pub fn set_precision(&mut self, precision: Option<usize>) {
+        self.interpreter.set_precision(precision);
+    }
+
+    /// Runs the source according to the mode, every error is reported as it
+    /// is found and returned as well, so that callers can inspect them.
+    /// The globals defined by the source stay defined for the next runs, a
+    /// script run from a file gets a `Lox` of its own.
+    /// The errors are reported as being in `file`, if given.
+    /// The source runs on a thread of its own, with a stack of `STACK_SIZE`,
+    /// while the calling thread waits for it.
+    pub fn run(&mut self, source: &str, file: Option<&str>) -> Result<(), Vec<LoxError>> {
+        let result = self.on_stack(|lox| lox.run_phases(source, file));
+
+        let timings = mem::take(&mut self.timings);
+        if self.time {
+            for (phase, duration) in timings {
+                self.report_line(format!(
+                    "phase={phase} ms={:.3}",
+                    duration.as_secs_f64() * 1000.0
+                ));
             }
-            Err(errors) => {
-                self.had_error = true;
-                dbg!(errors);
+        }
+
+        result
+    }
+
+    fn run_phases(&mut self, source: &str, file: Option<&str>) -> Result<(), Vec<LoxError>> {
+        let start = Instant::now();
+        let (tokens, errors) = lexer::scan_tokens(source);
+        self.timings.push(("lex", start.elapsed()));
+
+        let errors = self.report_all(source, file, errors);
+
+        match self.mode {
+            Mode::Tokens => {
+                for token in &tokens {
+                    self.emit(format!("{token} '{}'", token.lexeme()));
+                }
             }
+            Mode::Json => self.emit(lexer::tokens_json(&tokens)),
+            _ => {}
+        }
+
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        if matches!(self.mode, Mode::Tokens | Mode::Json) {
+            return Ok(());
+        }
+
+        let start = Instant::now();
+        let statements = Parser::new(tokens).parse_program();
+        self.timings.push(("parse", start.elapsed()));
+
+        let statements = match statements {
+            Ok(statements) => statements,
+            Err(errors) => return Err(self.report_all(source, file, errors)),
         };
+
+        let mut including = file
+            .and_then(|file| fs::canonicalize(file).ok())
+            .into_iter()
+            .collect();
+        let mut statements = self.expand_includes(statements, source, file, &mut including)?;
+
+        if self.mode == Mode::Ast {
+            for stmt in &statements {
+                self.emit(ast_printer::print_stmt(stmt));
+            }
+
+            return Ok(());
+        }
+
+        if self.mode == Mode::Dot {
+            self.emit(ast_dot::print_program(&statements));
+
+            return Ok(());
+        }
+
+        // the trace shows the statements as they were written
+        if !self.interpreter.trace() {
+            fold::fold_program(&mut statements);
+        }
+        let result = self.interpret(&statements);
+
+        result.map_err(|errors| self.report_all(source, file, errors))
     }
 
-    pub fn run_file(&mut self, path: &str) -> Result<(), Error> {
-        let source = fs::read_to_string(path)?;
+    /// Replaces the top level `include` statements with the statements of
+    /// the included files, which are relative to the directory of `file`.
+    /// `including` holds the files whose includes are being expanded, so
+    /// that a cycle is an error. The errors are reported already.
+    fn expand_includes(
+        &mut self,
+        statements: Vec<Stmt>,
+        source: &str,
+        file: Option<&str>,
+        including: &mut Vec<PathBuf>,
+    ) -> Result<Vec<Stmt>, Vec<LoxError>> {
+        let directory = file.and_then(|file| Path::new(file).parent());
+
+        let mut expanded = Vec::new();
+        let mut errors = Vec::new();
+
+        for stmt in statements {
+            let Stmt::Include { path } = stmt else {
+                expanded.push(stmt);
+                continue;
+            };
+
+            let TokenType::String(relative) = path.token_type() else {
+                unreachable!("not a path: {path}");
+            };
+            let included = directory.map_or_else(
+                || PathBuf::from(relative),
+                |directory| directory.join(relative),
+            );
+
+            let included_source = fs::canonicalize(&included).and_then(|canonical| {
+                fs::read_to_string(&canonical).map(|included_source| (canonical, included_source))
+            });
 
-        self.run(&source[0..source.len()]);
+            let error = match included_source {
+                Ok((canonical, _)) if including.contains(&canonical) => {
+                    ParseErrorType::CyclicInclude
+                }
+                Ok((canonical, included_source)) => {
+                    let included_file = included.to_string_lossy();
+
+                    including.push(canonical);
+                    let result = self.include(&included_source, &included_file, including);
+                    including.pop();
+
+                    match result {
+                        Ok(statements) => expanded.extend(statements),
+                        Err(included_errors) => errors.extend(included_errors),
+                    }
+
+                    continue;
+                }
+                Err(_) => ParseErrorType::IncludeNotFound,
+            };
 
-        Ok(())
+            errors.extend(self.report_all(source, file, vec![ParseError::new(error, &path)]));
+        }
+
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        Ok(expanded)
     }
 
-    pub fn run_promt(&mut self) {
-        let mut input = String::new();
+    /// Scans and parses an included file, then expands its own includes.
+    fn include(
+        &mut self,
+        source: &str,
+        file: &str,
+        including: &mut Vec<PathBuf>,
+    ) -> Result<Vec<Stmt>, Vec<LoxError>> {
+        let (tokens, errors) = lexer::scan_tokens(source);
+        if !errors.is_empty() {
+            return Err(self.report_all(source, Some(file), errors));
+        }
+
+        let statements = match Parser::new(tokens).parse_program() {
+            Ok(statements) => statements,
+            Err(errors) => return Err(self.report_all(source, Some(file), errors)),
+        };
 
-        print!("> ");
-        io::stdout().flush().unwrap();
+        self.expand_includes(statements, source, Some(file), including)
+    }
 
-        match io::stdin().read_line(&mut input) {
-            Ok(input_len) => {
-                self.run(&input);
+    /// Resolves the statements and runs them, unless only checking.
+    fn interpret(&mut self, statements: &[Stmt]) -> Result<(), Vec<LoxError>> {
+        let start = Instant::now();
+        let resolved = Resolver::new(&mut self.interpreter).resolve(statements);
+        self.timings.push(("resolve", start.elapsed()));
 
-                self.had_error = false;
+        if let Err(errors) = resolved {
+            return Err(errors.into_iter().map(LoxError::from).collect());
+        }
 
-                if input_len > 1 {
-                    self.run_promt();
+        if self.mode == Mode::Check {
+            return Ok(());
+        }
+
+        let start = Instant::now();
+        let result = self.interpreter.interpret(statements);
+        self.timings.push(("interpret", start.elapsed()));
+
+        result.map_err(|error| vec![LoxError::from(error)])
+    }
+
+    /// Evaluates the line as a bare expression and prints its value, returns
+    /// `false` if it isn't one so that it can be run as statements instead.
+    fn run_expression(&mut self, source: &str, file: Option<&str>) -> bool {
+        if self.mode != Mode::Run {
+            return false;
+        }
+
+        let (tokens, errors) = lexer::scan_tokens(source);
+        if !errors.is_empty() {
+            return false;
+        }
+
+        let Ok(mut expr) = Parser::new(tokens).parse_expression() else {
+            return false;
+        };
+        if !self.interpreter.trace() {
+            fold::fold_expression(&mut expr);
+        }
+
+        let result = self.on_stack(|lox| {
+            match Resolver::new(&mut lox.interpreter).resolve_expression(&expr) {
+                Ok(()) => lox
+                    .interpreter
+                    .evaluate(&expr)
+                    .map_err(|error| vec![LoxError::from(error)]),
+                Err(errors) => Err(errors.into_iter().map(LoxError::from).collect()),
+            }
+        });
+
+        match result {
+            Ok(value) => {
+                let precision = self.interpreter.precision();
+                self.emit(value.display_with(precision));
+            }
+            Err(errors) => {
+                self.report_all(source, file, errors);
+            }
+        }
+
+        true
+    }
+
+    /// Runs `f` on a thread with a stack of `STACK_SIZE` and waits for it, a
+    /// panic of `f` is resumed in the calling thread.
+    fn on_stack<R>(&mut self, f: impl FnOnce(&mut Self) -> R) -> R {
+        let (lox, f) = (Unshared(self), Unshared(f));
+
+        thread::scope(|scope| {
+            let thread = thread::Builder::new()
+                .stack_size(STACK_SIZE)
+                .spawn_scoped(scope, move || {
+                    let (lox, f) = (lox.into_inner(), f.into_inner());
+
+                    Unshared(f(lox))
+                })
+                .expect("the thread running the program should start");
+
+            match thread.join() {
+                Ok(result) => result.into_inner(),
+                Err(payload) => panic::resume_unwind(payload),
+            }
+        })
+    }
+
+    fn emit(&mut self, line: impl Display) {
+        if let Err(error) = writeln!(self.interpreter.output(), "{line}") {
+            warn!("Could not write the output: {error}");
+        }
+    }
+
+    /// Reports the errors of a stage, lexing and parse errors underline the
+    /// lexeme they were found at.
+    fn report_all(
+        &mut self,
+        source: &str,
+        file: Option<&str>,
+        errors: Vec<impl Into<LoxError>>,
+    ) -> Vec<LoxError> {
+        let errors = errors.into_iter().map(Into::into).collect::<Vec<_>>();
+
+        for error in &errors {
+            let message = error.located(file);
+
+            match error {
+                LoxError::Lex(lexing_error) => {
+                    self.report_span(source, lexing_error.span(), &message);
+                }
+                LoxError::Parse(parse_error) => {
+                    self.report_span(source, parse_error.span(), &message);
+                }
+                _ => self.report(&message),
+            }
+        }
+
+        errors
+    }
+
+    fn report(&mut self, error: &impl Display) {
+        let error = error.to_string();
+
+        match error.split_once("Error: ") {
+            Some((location, message)) if self.use_color => self.report_line(format!(
+                "{location}\x1b[1;31mError\x1b[0m: \x1b[1m{message}\x1b[0m"
+            )),
+            _ => self.report_line(error),
+        }
+    }
+
+    fn report_line(&mut self, line: impl Display) {
+        if let Err(error) = writeln!(self.error_output, "{line}") {
+            warn!("Could not report the error: {error}");
+        }
+    }
+
+    /// Reports the error along with the source line it is on, the lexeme
+    /// at `span` is underlined with carets.
+    pub fn report_span(&mut self, source: &str, span: &Span, message: &str) {
+        let line_start = source[..span.start]
+            .rfind('\n')
+            .map_or(0, |index| index + 1);
+        let line_end = source[span.start..]
+            .find('\n')
+            .map_or(source.len(), |index| span.start + index);
+
+        let column = source[line_start..span.start].chars().count();
+        // at least one caret, so that the end of the input can be pointed at
+        let width = source[span.start..span.end.min(line_end)]
+            .chars()
+            .count()
+            .max(1);
+
+        self.report(&message);
+        self.report_line(format!("    {}", &source[line_start..line_end]));
+        self.report_line(format!("    {}{}", " ".repeat(column), "^".repeat(width)));
+    }
+
+    /// Runs the script at `path`, or the one piped through stdin if the
+    /// path is `-`.
+    /// Exits with 66 if the script can't be read, and with the exit code of
+    /// its errors otherwise.
+    pub fn run_file(&mut self, path: &str) {
+        let source = if path == "-" {
+            io::read_to_string(io::stdin())
+        } else {
+            fs::read_to_string(path)
+        };
+
+        let source = match source {
+            Ok(source) => source,
+            Err(error) => {
+                self.report(&format!("Error: could not read '{path}': {error}"));
+                exit(66);
+            }
+        };
+
+        let file = if path == "-" { "<stdin>" } else { path };
+
+        if let Err(errors) = self.run(&source[0..source.len()], Some(file)) {
+            // a runtime error stops the program, so it can only be the last one
+            exit(errors.last().map_or(65, LoxError::exit_code));
+        }
+    }
+
+    /// Runs the lines read from `reader` one by one, until its end or
+    /// `.exit`.
+    pub fn run_promt(&mut self, mut reader: impl BufRead) {
+        self.history = self
+            .history_path
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .map(|history| history.lines().map(str::to_owned).collect())
+            .unwrap_or_default();
+
+        self.history_file = self.history_path.as_ref().and_then(|path| {
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .inspect_err(|error| warn!("Could not save the promt history: {error}"))
+                .ok()
+        });
+
+        loop {
+            let mut input = String::new();
+
+            let output = self.interpreter.output();
+            if let Err(error) = write!(output, "> ").and_then(|()| output.flush()) {
+                warn!("Could not write the output: {error}");
+            }
+
+            match reader.read_line(&mut input) {
+                // end of the input
+                Ok(0) => break,
+                Ok(_) if input.trim() == ".exit" => break,
+                Ok(_) if input.trim() == ".history" => {
+                    let history = self
+                        .history
+                        .iter()
+                        .enumerate()
+                        .map(|(number, line)| format!("{:>4}  {line}", number + 1))
+                        .collect::<Vec<_>>();
+
+                    for line in history {
+                        self.emit(line);
+                    }
+                }
+                Ok(_) if input.trim() == ".env" => {
+                    for (name, value) in self.interpreter.globals() {
+                        self.emit(format!("{name} = {value}"));
+                    }
                 }
+                Ok(_) if input.split_whitespace().next() == Some(".load") => {
+                    self.remember(input.trim_end());
+
+                    match input.trim().strip_prefix(".load").map(str::trim) {
+                        Some(path) if !path.is_empty() => self.load(path),
+                        _ => self.emit("Usage: .load <file>"),
+                    }
+                }
+                Ok(_) => {
+                    self.remember(input.trim_end());
+
+                    // the errors have been reported already
+                    if !self.run_expression(&input, Some(REPL_FILE)) {
+                        let _ = self.run(&input, Some(REPL_FILE));
+                    }
+                }
+                Err(error) => self.emit(format!("Line is not a valid UTF-8: {error}")),
+            }
+        }
+
+        self.history_file = None;
+    }
+
+    /// Runs the script at `path` in the session of the promt, so that its
+    /// definitions can be used by the next lines. Its errors are only
+    /// reported.
+    fn load(&mut self, path: &str) {
+        match fs::read_to_string(path) {
+            Ok(source) => {
+                let _ = self.run(&source, Some(path));
             }
-            Err(error) => println!("Line is not a valid UTF-8: {error}"),
+            Err(error) => self.report(&format!("Error: could not read '{path}': {error}")),
+        }
+    }
+
+    /// Adds the line to the history, it is saved right away so that nothing
+    /// is lost if the session ends abruptly.
+    fn remember(&mut self, line: &str) {
+        if line.is_empty() {
+            return;
+        }
+
+        self.history.push(line.to_owned());
+
+        let Some(file) = &mut self.history_file else {
+            return;
+        };
+
+        if let Err(error) = writeln!(file, "{line}") {
+            warn!("Could not save the promt history: {error}");
         }
     }
 }
+
+/// Hands the state of `Lox`, which is full of `Rc`s, to the thread that runs
+/// the program.
+struct Unshared<T>(T);
+
+impl<T> Unshared<T> {
+    // taking `self` whole, so that closures capture the wrapper rather than
+    // its field
+    fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+// SAFETY: the values are only sent to a scoped thread that the sending thread
+// waits for, so nothing in them is ever used by two threads at once. The
+// output given to `Lox` is `Send` itself, which rules out writers that are
+// tied to their thread.
+unsafe impl<T> Send for Unshared<T> {}
@@ -1,34 +1,70 @@
-use std::{env, io::Error};
+use std::{env, io, process::exit};
 
-use log::info;
+use log::{info, LevelFilter};
 
-use crate::lox::Lox;
+use rlox::lox::{Lox, Mode};
 
-mod lexer;
-mod lox;
+const USAGE: &str =
+    "Usage: rlox [--tokens | --ast | --emit=json | --emit=dot | --check] [--trace] [--time] [--precision=N] [script]";
 
-fn main() -> Result<(), Error> {
-    env_logger::init();
+fn main() {
+    // flags select the mode, whatever is left is the script to run
+    let (flags, args): (Vec<String>, Vec<String>) =
+        env::args().skip(1).partition(|arg| arg.starts_with("--"));
 
-    let args = env::args();
+    let mut mode = Mode::default();
+    let mut trace = false;
+    let mut time = false;
+    let mut precision = None;
+    for flag in &flags {
+        if let Some(digits) = flag.strip_prefix("--precision=") {
+            if let Ok(digits) = digits.parse() {
+                precision = Some(digits);
+                continue;
+            }
+        }
 
-    // if args len equal to 1 that means that there are no argument have been provided
-    // if args len is equal to 2 there should be a file
-    // if args len is more than 2 there should be input for the promt
-    let mut lox = Lox::default();
-    if args.len() == 1 {
-        info!("Start promt");
+        match flag.as_str() {
+            "--tokens" => mode = Mode::Tokens,
+            "--ast" => mode = Mode::Ast,
+            "--emit=json" => mode = Mode::Json,
+            "--emit=dot" => mode = Mode::Dot,
+            "--check" => mode = Mode::Check,
+            "--trace" => trace = true,
+            "--time" => time = true,
+            _ => usage(),
+        }
+    }
+
+    // the trace is logged at debug level, which is hidden by default
+    let mut logger = env_logger::Builder::from_default_env();
+    if trace {
+        logger.filter_module("rlox::interpreter", LevelFilter::Debug);
+    }
+    logger.init();
 
-        lox.run_promt();
-    } else if args.len() == 2 {
-        info!("Start promt");
+    // without a script there should be input for the promt
+    let mut lox = Lox::new(mode);
+    lox.set_trace(trace);
+    lox.set_time(time);
+    lox.set_precision(precision);
+    match args.as_slice() {
+        [] => {
+            info!("Start promt");
 
-        let args: Vec<String> = env::args().collect();
+            lox.run_promt(io::stdin().lock());
+        }
+        [path] => {
+            info!("Start promt");
 
-        lox.run_file(&args[0])?;
-    } else if args.len() > 2 {
-        info!("Usage: rlox [script]");
+            lox.run_file(path);
+        }
+        _ => usage(),
     }
+}
 
-    Ok(())
+/// Exits with 64, the sysexits code for a wrong command line.
+fn usage() -> ! {
+    eprintln!("{USAGE}");
+    exit(64);
 }
@@ -0,0 +1,119 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::{
+    callable::{Callable, NativeResult},
+    interpreter::{Interpreter, RuntimeErrorType, Value},
+};
+
+/// Registers the native functions that every program can call.
+pub fn define_natives(interpreter: &mut Interpreter) {
+    interpreter.define_native("clock", 0, clock);
+    interpreter.define_native("len", 1, len);
+    interpreter.define_native("substr", 3, substr);
+    interpreter.define_native("str", 1, str);
+    interpreter.define_native("num", 1, num);
+    interpreter.define_native("arity", 1, arity);
+    interpreter.define_native("is_nan", 1, is_nan);
+}
+
+/// Seconds since the Unix epoch.
+fn clock(_arguments: &[Value]) -> NativeResult {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+
+    Ok(Value::Number(now.as_secs_f64()))
+}
+
+/// Length of a list or a map, or of a string in characters rather than
+/// bytes.
+fn len(arguments: &[Value]) -> NativeResult {
+    match &arguments[0] {
+        Value::List(list) => return Ok(Value::Int(list.borrow().len() as i64)),
+        Value::Map(map) => return Ok(Value::Int(map.borrow().len() as i64)),
+        _ => {}
+    }
+
+    let string = string_argument(&arguments[0])?;
+
+    Ok(Value::Int(string.chars().count() as i64))
+}
+
+/// Characters of the string from `start` up to, but not including, `end`.
+fn substr(arguments: &[Value]) -> NativeResult {
+    let string = string_argument(&arguments[0])?;
+    let start = index_argument(&arguments[1])?;
+    let end = index_argument(&arguments[2])?;
+
+    if start > end || end > string.chars().count() {
+        return Err(RuntimeErrorType::IndexOutOfRange);
+    }
+
+    let substring = string.chars().skip(start).take(end - start).collect();
+
+    Ok(Value::Str(substring))
+}
+
+/// The value as `print` shows it.
+fn str(arguments: &[Value]) -> NativeResult {
+    Ok(Value::Str(arguments[0].to_string()))
+}
+
+/// Parses the string as a number, surrounding whitespace is ignored. Strings
+/// of digits give an integer if it fits.
+fn num(arguments: &[Value]) -> NativeResult {
+    let string = string_argument(&arguments[0])?.trim();
+
+    if let Ok(integer) = string.parse() {
+        return Ok(Value::Int(integer));
+    }
+
+    match string.parse() {
+        Ok(number) => Ok(Value::Number(number)),
+        Err(_) => Err(RuntimeErrorType::InvalidArgument(
+            "Argument must be a number string.",
+        )),
+    }
+}
+
+/// Number of parameters of a function, or of the `init` method of a class,
+/// a rest parameter isn't counted.
+fn arity(arguments: &[Value]) -> NativeResult {
+    match &arguments[0] {
+        Value::Callable(callable) => Ok(Value::Int(callable.arity() as i64)),
+        Value::Class(class) => Ok(Value::Int(class.arity() as i64)),
+        _ => Err(RuntimeErrorType::InvalidArgument(
+            "Argument must be a function or a class.",
+        )),
+    }
+}
+
+/// `NaN` isn't equal to itself, so `x == x` can't tell whether it is one.
+fn is_nan(arguments: &[Value]) -> NativeResult {
+    match &arguments[0] {
+        Value::Int(_) => Ok(Value::Bool(false)),
+        Value::Number(number) => Ok(Value::Bool(number.is_nan())),
+        _ => Err(RuntimeErrorType::InvalidArgument(
+            "Argument must be a number.",
+        )),
+    }
+}
+
+fn string_argument(value: &Value) -> Result<&str, RuntimeErrorType> {
+    match value {
+        Value::Str(string) => Ok(string),
+        _ => Err(RuntimeErrorType::InvalidArgument(
+            "Argument must be a string.",
+        )),
+    }
+}
+
+fn index_argument(value: &Value) -> Result<usize, RuntimeErrorType> {
+    match value {
+        Value::Int(_) | Value::Number(_) => value
+            .as_integer()
+            .and_then(|index| usize::try_from(index).ok())
+            .ok_or(RuntimeErrorType::IndexOutOfRange),
+        _ => Err(RuntimeErrorType::InvalidArgument("Index must be a number.")),
+    }
+}
@@ -0,0 +1,1470 @@
+use std::{
+    fmt,
+    rc::Rc,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::lexer::{Span, StringPart, Token, TokenType};
+
+#[derive(Debug)]
+pub enum Literal {
+    Int(i64),
+    Number(f64),
+    String(String),
+    Bool(bool),
+    Nil,
+}
+
+/// Identifies the variable expressions resolved to a local scope, ids are
+/// unique across parsers so that the REPL lines don't mix them up.
+pub type ExprId = usize;
+
+fn next_expr_id() -> ExprId {
+    static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+    NEXT_ID.fetch_add(1, Ordering::Relaxed)
+}
+
+#[derive(Debug)]
+pub enum Expr {
+    Binary {
+        left: Box<Expr>,
+        operator: Token,
+        right: Box<Expr>,
+    },
+    Unary {
+        operator: Token,
+        right: Box<Expr>,
+    },
+    // operators written after their operand, like the `!` of factorials
+    Postfix {
+        left: Box<Expr>,
+        operator: Token,
+    },
+    Literal(Literal),
+    Grouping(Box<Expr>),
+    Variable {
+        id: ExprId,
+        name: Token,
+    },
+    Assign {
+        id: ExprId,
+        name: Token,
+        value: Box<Expr>,
+    },
+    Logical {
+        left: Box<Expr>,
+        operator: Token,
+        right: Box<Expr>,
+    },
+    Call {
+        callee: Box<Expr>,
+        paren: Token,
+        arguments: Vec<Expr>,
+    },
+    Get {
+        object: Box<Expr>,
+        name: Token,
+    },
+    Set {
+        object: Box<Expr>,
+        name: Token,
+        value: Box<Expr>,
+        // the binary operator of `object.name op= value`, the object is
+        // evaluated once for both reading and writing the property
+        operator: Option<Token>,
+    },
+    Lambda(Rc<Function>),
+    // string literal parts and expressions, concatenated when evaluated
+    Interpolation(Vec<Expr>),
+    Conditional {
+        condition: Box<Expr>,
+        then_branch: Box<Expr>,
+        else_branch: Box<Expr>,
+    },
+    List {
+        bracket: Token,
+        elements: Vec<Expr>,
+    },
+    Map {
+        brace: Token,
+        entries: Vec<(Expr, Expr)>,
+    },
+    // the bracket is the closing one, which the errors are reported at
+    Index {
+        object: Box<Expr>,
+        bracket: Token,
+        index: Box<Expr>,
+    },
+    SetIndex {
+        object: Box<Expr>,
+        bracket: Token,
+        index: Box<Expr>,
+        value: Box<Expr>,
+        // same as for `Set`
+        operator: Option<Token>,
+    },
+    This {
+        id: ExprId,
+        keyword: Token,
+    },
+    Super {
+        id: ExprId,
+        keyword: Token,
+        method: Token,
+    },
+}
+
+impl Expr {
+    /// Line of the first token of the expression that is kept in the tree,
+    /// literals keep none.
+    pub fn line(&self) -> Option<usize> {
+        let token = match self {
+            Expr::Binary { left, .. } | Expr::Logical { left, .. } => return left.line(),
+            Expr::Call { callee: object, .. }
+            | Expr::Get { object, .. }
+            | Expr::Set { object, .. }
+            | Expr::Index { object, .. }
+            | Expr::SetIndex { object, .. } => return object.line(),
+            Expr::Conditional { condition, .. } => return condition.line(),
+            Expr::Grouping(expr) => return expr.line(),
+            Expr::Interpolation(parts) => return parts.iter().find_map(Expr::line),
+            Expr::Literal(_) => return None,
+            Expr::Unary { operator, .. } | Expr::Postfix { operator, .. } => operator,
+            Expr::Variable { name, .. } | Expr::Assign { name, .. } => name,
+            Expr::Lambda(function) => &function.name,
+            Expr::List { bracket, .. } => bracket,
+            Expr::Map { brace, .. } => brace,
+            Expr::This { keyword, .. } | Expr::Super { keyword, .. } => keyword,
+        };
+
+        Some(token.span().line)
+    }
+}
+
+#[derive(Debug)]
+pub struct Function {
+    pub name: Token,
+    pub params: Vec<Token>,
+    // values of the trailing parameters that can be left out of calls
+    pub defaults: Vec<Expr>,
+    // `...name`, bound to a list of the arguments after the parameters
+    pub rest: Option<Token>,
+    pub body: Vec<Stmt>,
+    // a method without a parameter list, called when the property is read
+    pub is_getter: bool,
+}
+
+impl Function {
+    /// Number of parameters that don't have a default value.
+    pub fn required_params(&self) -> usize {
+        self.params.len() - self.defaults.len()
+    }
+}
+
+#[derive(Debug)]
+pub enum Stmt {
+    Expression(Expr),
+    Print(Expr),
+    // fails with the message, if given, when the condition is falsey
+    Assert {
+        keyword: Token,
+        condition: Expr,
+        message: Option<Expr>,
+    },
+    Var {
+        name: Token,
+        initializer: Option<Expr>,
+    },
+    Block(Vec<Stmt>),
+    If {
+        condition: Expr,
+        then_branch: Box<Stmt>,
+        else_branch: Option<Box<Stmt>>,
+    },
+    While {
+        condition: Expr,
+        body: Box<Stmt>,
+        // run after every iteration, `for` loops keep it apart from the body
+        // so that `continue` doesn't skip it
+        increment: Option<Expr>,
+    },
+    // runs the body once before the condition is first checked
+    DoWhile {
+        body: Box<Stmt>,
+        condition: Expr,
+    },
+    // runs the body for every element of a list or character of a string
+    ForIn {
+        name: Token,
+        iterable: Expr,
+        body: Box<Stmt>,
+    },
+    // runs the first case whose value equals the subject, cases don't fall
+    // through to the next one
+    Switch {
+        subject: Expr,
+        cases: Vec<(Expr, Vec<Stmt>)>,
+        default: Option<Vec<Stmt>>,
+    },
+    Break(Token),
+    Continue(Token),
+    Throw {
+        keyword: Token,
+        value: Expr,
+    },
+    // the thrown value is bound to `name` in the handler
+    Try {
+        body: Vec<Stmt>,
+        name: Token,
+        handler: Vec<Stmt>,
+    },
+    // shared, so that function values can outlive the parsed program
+    Function(Rc<Function>),
+    Class {
+        name: Token,
+        // always a `Variable`
+        superclass: Option<Expr>,
+        methods: Vec<Rc<Function>>,
+        // called on the class rather than on its instances
+        static_methods: Vec<Rc<Function>>,
+    },
+    Return {
+        keyword: Token,
+        value: Option<Expr>,
+    },
+    // only at the top level, `Lox` replaces it with the statements of the
+    // file before the program is resolved
+    Include {
+        // always a `String`
+        path: Token,
+    },
+}
+
+impl Stmt {
+    /// Line the statement starts at, if any of its tokens are kept.
+    pub fn line(&self) -> Option<usize> {
+        let token = match self {
+            Stmt::Expression(expr) | Stmt::Print(expr) => return expr.line(),
+            Stmt::If { condition, .. }
+            | Stmt::While { condition, .. }
+            | Stmt::DoWhile { condition, .. } => return condition.line(),
+            Stmt::Switch { subject, .. } => return subject.line(),
+            Stmt::ForIn { name, .. } => name,
+            Stmt::Include { path } => path,
+            Stmt::Block(statements) => return statements.iter().find_map(Stmt::line),
+            Stmt::Var { name, .. } | Stmt::Class { name, .. } => name,
+            Stmt::Assert { keyword, .. } | Stmt::Throw { keyword, .. } => keyword,
+            Stmt::Try { body, .. } => return body.iter().find_map(Stmt::line),
+            Stmt::Break(keyword) | Stmt::Continue(keyword) | Stmt::Return { keyword, .. } => {
+                keyword
+            }
+            Stmt::Function(function) => &function.name,
+        };
+
+        Some(token.span().line)
+    }
+}
+
+#[derive(Debug)]
+pub enum ParseErrorType {
+    ExpectedExpression,
+    ExpectedLeftParen,
+    ExpectedRightParen,
+    ExpectedRightBrace,
+    ExpectedRightBracket,
+    ExpectedSemicolon,
+    ExpectedVariableName,
+    ExpectedFunctionName,
+    ExpectedClassName,
+    ExpectedSuperclassName,
+    ExpectedDot,
+    ExpectedPropertyName,
+    ExpectedParameterName,
+    ExpectedLeftBrace,
+    InvalidAssignmentTarget,
+    InvalidIncrementTarget,
+    ExpectedEndOfExpression,
+    ExpectedColon,
+    ExpectedMapColon,
+    ExpectedCase,
+    ExpectedCaseColon,
+    RequiredParameterAfterDefault,
+    RestParameterNotLast,
+    ExpectedCatch,
+    ExpectedCatchName,
+    ExpectedWhile,
+    ChainedComparison,
+    ExpectedIncludePath,
+    IncludeNotFound,
+    CyclicInclude,
+}
+
+#[derive(Debug)]
+pub struct ParseError {
+    t: ParseErrorType,
+    // position of the token the error was found at
+    span: Span,
+    column: usize,
+    file: Option<Rc<str>>,
+}
+
+impl ParseError {
+    pub fn new(t: ParseErrorType, token: &Token) -> Self {
+        Self {
+            t,
+            span: *token.span(),
+            column: token.column(),
+            file: token.file().cloned(),
+        }
+    }
+
+    pub fn file(&self) -> Option<&str> {
+        self.file.as_deref()
+    }
+
+    pub fn span(&self) -> &Span {
+        &self.span
+    }
+
+    pub fn line(&self) -> usize {
+        self.span.line
+    }
+
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
+    pub fn error_type(&self) -> &ParseErrorType {
+        &self.t
+    }
+}
+
+impl fmt::Display for ParseErrorType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = match self {
+            ParseErrorType::ExpectedExpression => "Expect expression.",
+            ParseErrorType::ExpectedLeftParen => "Expect '('.",
+            ParseErrorType::ExpectedRightParen => "Expect ')'.",
+            ParseErrorType::ExpectedRightBrace => "Expect '}' after block.",
+            ParseErrorType::ExpectedRightBracket => "Expect ']'.",
+            ParseErrorType::ExpectedSemicolon => "Expect ';' after statement.",
+            ParseErrorType::ExpectedVariableName => "Expect variable name.",
+            ParseErrorType::ExpectedFunctionName => "Expect function name.",
+            ParseErrorType::ExpectedClassName => "Expect class name.",
+            ParseErrorType::ExpectedSuperclassName => "Expect superclass name.",
+            ParseErrorType::ExpectedDot => "Expect '.' after 'super'.",
+            ParseErrorType::ExpectedPropertyName => "Expect property name after '.'.",
+            ParseErrorType::ExpectedParameterName => "Expect parameter name.",
+            ParseErrorType::ExpectedLeftBrace => "Expect '{' before body.",
+            ParseErrorType::InvalidAssignmentTarget => "Invalid assignment target.",
+            ParseErrorType::InvalidIncrementTarget => "Can only increment or decrement a variable.",
+            ParseErrorType::ExpectedEndOfExpression => "Expect end of expression.",
+            ParseErrorType::ExpectedColon => "Expect ':' in conditional expression.",
+            ParseErrorType::ExpectedMapColon => "Expect ':' after map key.",
+            ParseErrorType::ExpectedCase => "Expect 'case' or 'default'.",
+            ParseErrorType::ExpectedCaseColon => "Expect ':' after case.",
+            ParseErrorType::RequiredParameterAfterDefault => {
+                "Parameter without a default can't follow one with a default."
+            }
+            ParseErrorType::RestParameterNotLast => "Rest parameter must be the last one.",
+            ParseErrorType::ExpectedCatch => "Expect 'catch' after try block.",
+            ParseErrorType::ExpectedCatchName => "Expect name of the caught value.",
+            ParseErrorType::ExpectedWhile => "Expect 'while' after do body.",
+            ParseErrorType::ChainedComparison => "Chained comparisons are not allowed; use 'and'.",
+            ParseErrorType::ExpectedIncludePath => "Expect path string after 'include'.",
+            ParseErrorType::IncludeNotFound => "Could not read the included file.",
+            ParseErrorType::CyclicInclude => "File is already being included.",
+        };
+
+        write!(f, "{message}")
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[line {}] Error: {}", self.span.line, self.t)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Desugars `name op= value` into `name = name op value`, `++name` and
+/// `--name` are desugared the same way with a value of 1. Properties and
+/// elements keep the operator instead, so that the object and the index are
+/// evaluated once. `None` if the target can't be assigned to.
+fn compound_assignment(target: Expr, operator: &Token, value: Expr) -> Option<Expr> {
+    let binary_type = match operator.token_type() {
+        TokenType::PlusEqual | TokenType::PlusPlus => TokenType::Plus,
+        TokenType::MinusEqual | TokenType::MinusMinus => TokenType::Minus,
+        TokenType::StarEqual => TokenType::Star,
+        TokenType::SlashEqual => TokenType::Slash,
+        operator_type => unreachable!("not a compound assignment: {operator_type:?}"),
+    };
+
+    let operator = Token::new(binary_type, *operator.span(), operator.column());
+
+    let expr = match target {
+        Expr::Variable { name, .. } => Expr::Assign {
+            id: next_expr_id(),
+            name: name.clone(),
+            value: Box::new(Expr::Binary {
+                left: Box::new(Expr::Variable {
+                    id: next_expr_id(),
+                    name,
+                }),
+                operator,
+                right: Box::new(value),
+            }),
+        },
+        Expr::Get { object, name } => Expr::Set {
+            object,
+            name,
+            value: Box::new(value),
+            operator: Some(operator),
+        },
+        Expr::Index {
+            object,
+            bracket,
+            index,
+        } => Expr::SetIndex {
+            object,
+            bracket,
+            index,
+            value: Box::new(value),
+            operator: Some(operator),
+        },
+        _ => return None,
+    };
+
+    Some(expr)
+}
+
+/// Recursive descent parser over the scanned tokens, the token vector is
+/// expected to be terminated with `Eof`.
+pub struct Parser {
+    tokens: Vec<Token>,
+    current: usize,
+}
+
+impl Parser {
+    pub fn new(tokens: Vec<Token>) -> Self {
+        Self { tokens, current: 0 }
+    }
+
+    /// Parses declarations until `Eof`, after an error the parser skips to
+    /// the next statement so that every error of the program is collected.
+    pub fn parse_program(&mut self) -> Result<Vec<Stmt>, Vec<ParseError>> {
+        let mut statements = Vec::new();
+        let mut errors = Vec::new();
+
+        while !self.is_at_end() {
+            let result = if self.match_types(&[TokenType::Include]) {
+                self.include()
+            } else {
+                self.declaration()
+            };
+
+            match result {
+                Ok(stmt) => statements.push(stmt),
+                Err(error) => {
+                    errors.push(error);
+                    self.synchronize();
+                }
+            }
+        }
+
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        Ok(statements)
+    }
+
+    /// Parses a single expression that must span every token, which is how
+    /// the promt tells a bare expression apart from statements.
+    pub fn parse_expression(&mut self) -> Result<Expr, ParseError> {
+        let expr = self.expression()?;
+
+        if !self.is_at_end() {
+            return Err(self.error(ParseErrorType::ExpectedEndOfExpression));
+        }
+
+        Ok(expr)
+    }
+
+    fn declaration(&mut self) -> Result<Stmt, ParseError> {
+        if self.match_types(&[TokenType::Class]) {
+            return self.class_declaration();
+        }
+
+        // without a name it is a lambda, which is parsed as an expression
+        if self.check(&TokenType::Fun) && self.check_ahead(1, &TokenType::Identifier(String::new()))
+        {
+            self.advance();
+
+            return Ok(Stmt::Function(Rc::new(self.function()?)));
+        }
+
+        if self.match_types(&[TokenType::Var]) {
+            return self.var_declaration();
+        }
+
+        self.statement()
+    }
+
+    /// `include "path";`
+    fn include(&mut self) -> Result<Stmt, ParseError> {
+        let path = self.peek().clone();
+
+        if !matches!(path.token_type(), TokenType::String(_)) {
+            return Err(self.error(ParseErrorType::ExpectedIncludePath));
+        }
+
+        self.advance();
+        self.consume(&TokenType::Semicolon, ParseErrorType::ExpectedSemicolon)?;
+
+        Ok(Stmt::Include { path })
+    }
+
+    fn class_declaration(&mut self) -> Result<Stmt, ParseError> {
+        let name = self.consume_identifier(ParseErrorType::ExpectedClassName)?;
+
+        let superclass = if self.match_types(&[TokenType::Less]) {
+            let name = self.consume_identifier(ParseErrorType::ExpectedSuperclassName)?;
+
+            Some(Expr::Variable {
+                id: next_expr_id(),
+                name,
+            })
+        } else {
+            None
+        };
+
+        self.consume(&TokenType::LeftBrace, ParseErrorType::ExpectedLeftBrace)?;
+
+        let mut methods = Vec::new();
+        let mut static_methods = Vec::new();
+        while !self.check(&TokenType::RightBrace) && !self.is_at_end() {
+            if self.match_types(&[TokenType::Static]) {
+                static_methods.push(Rc::new(self.function()?));
+            } else {
+                methods.push(Rc::new(self.method()?));
+            }
+        }
+
+        self.consume(&TokenType::RightBrace, ParseErrorType::ExpectedRightBrace)?;
+
+        Ok(Stmt::Class {
+            name,
+            superclass,
+            methods,
+            static_methods,
+        })
+    }
+
+    fn function(&mut self) -> Result<Function, ParseError> {
+        let name = self.consume_identifier(ParseErrorType::ExpectedFunctionName)?;
+
+        self.function_body(name)
+    }
+
+    /// Same as `function`, but a method can leave out the parameter list to
+    /// be a getter.
+    fn method(&mut self) -> Result<Function, ParseError> {
+        let name = self.consume_identifier(ParseErrorType::ExpectedFunctionName)?;
+
+        if !self.match_types(&[TokenType::LeftBrace]) {
+            return self.function_body(name);
+        }
+
+        Ok(Function {
+            name,
+            params: Vec::new(),
+            defaults: Vec::new(),
+            rest: None,
+            body: self.block()?,
+            is_getter: true,
+        })
+    }
+
+    /// Parses the parameters and body of a function, the name has been
+    /// consumed already.
+    fn function_body(&mut self, name: Token) -> Result<Function, ParseError> {
+        self.consume(&TokenType::LeftParen, ParseErrorType::ExpectedLeftParen)?;
+
+        let mut params = Vec::new();
+        let mut defaults = Vec::new();
+        let mut rest = None;
+        if !self.check(&TokenType::RightParen) {
+            loop {
+                if self.match_types(&[TokenType::DotDotDot]) {
+                    rest = Some(self.consume_identifier(ParseErrorType::ExpectedParameterName)?);
+
+                    if self.check(&TokenType::Comma) {
+                        return Err(self.error(ParseErrorType::RestParameterNotLast));
+                    }
+
+                    break;
+                }
+
+                let param = self.consume_identifier(ParseErrorType::ExpectedParameterName)?;
+
+                if self.match_types(&[TokenType::Equal]) {
+                    defaults.push(self.expression()?);
+                } else if !defaults.is_empty() {
+                    return Err(ParseError::new(
+                        ParseErrorType::RequiredParameterAfterDefault,
+                        &param,
+                    ));
+                }
+
+                params.push(param);
+
+                if !self.match_types(&[TokenType::Comma]) {
+                    break;
+                }
+            }
+        }
+
+        self.consume(&TokenType::RightParen, ParseErrorType::ExpectedRightParen)?;
+        self.consume(&TokenType::LeftBrace, ParseErrorType::ExpectedLeftBrace)?;
+
+        let body = self.block()?;
+
+        Ok(Function {
+            name,
+            params,
+            defaults,
+            rest,
+            body,
+            is_getter: false,
+        })
+    }
+
+    fn var_declaration(&mut self) -> Result<Stmt, ParseError> {
+        let name = self.consume_identifier(ParseErrorType::ExpectedVariableName)?;
+
+        let initializer = if self.match_types(&[TokenType::Equal]) {
+            Some(self.expression()?)
+        } else {
+            None
+        };
+
+        self.consume(&TokenType::Semicolon, ParseErrorType::ExpectedSemicolon)?;
+
+        Ok(Stmt::Var { name, initializer })
+    }
+
+    fn statement(&mut self) -> Result<Stmt, ParseError> {
+        if self.match_types(&[TokenType::For]) {
+            return self.for_statement();
+        }
+
+        if self.match_types(&[TokenType::If]) {
+            return self.if_statement();
+        }
+
+        if self.match_types(&[TokenType::While]) {
+            return self.while_statement();
+        }
+
+        if self.match_types(&[TokenType::Do]) {
+            return self.do_while_statement();
+        }
+
+        if self.match_types(&[TokenType::Switch]) {
+            return self.switch_statement();
+        }
+
+        if self.match_types(&[TokenType::Try]) {
+            return self.try_statement();
+        }
+
+        if self.match_types(&[TokenType::Throw]) {
+            let keyword = self.previous().clone();
+            let value = self.expression()?;
+            self.consume(&TokenType::Semicolon, ParseErrorType::ExpectedSemicolon)?;
+
+            return Ok(Stmt::Throw { keyword, value });
+        }
+
+        if self.match_types(&[TokenType::Return]) {
+            return self.return_statement();
+        }
+
+        if self.match_types(&[TokenType::Break, TokenType::Continue]) {
+            let keyword = self.previous().clone();
+            self.consume(&TokenType::Semicolon, ParseErrorType::ExpectedSemicolon)?;
+
+            return Ok(match keyword.token_type() {
+                TokenType::Break => Stmt::Break(keyword),
+                _ => Stmt::Continue(keyword),
+            });
+        }
+
+        if self.match_types(&[TokenType::Assert]) {
+            return self.assert_statement();
+        }
+
+        if self.match_types(&[TokenType::Print]) {
+            let value = self.expression()?;
+            self.consume(&TokenType::Semicolon, ParseErrorType::ExpectedSemicolon)?;
+
+            return Ok(Stmt::Print(value));
+        }
+
+        // a block never starts with `key:`, so that is a map literal instead
+        if self.check(&TokenType::LeftBrace) && !self.check_ahead(2, &TokenType::Colon) {
+            self.advance();
+
+            return Ok(Stmt::Block(self.block()?));
+        }
+
+        let expr = self.expression()?;
+        self.consume(&TokenType::Semicolon, ParseErrorType::ExpectedSemicolon)?;
+
+        Ok(Stmt::Expression(expr))
+    }
+
+    /// Desugars `for (initializer; condition; increment) body` into a `while`
+    /// loop with an increment, wrapped in a block that scopes the initializer.
+    fn for_statement(&mut self) -> Result<Stmt, ParseError> {
+        self.consume(&TokenType::LeftParen, ParseErrorType::ExpectedLeftParen)?;
+
+        if self.check(&TokenType::Identifier(String::new())) && self.check_ahead(1, &TokenType::In)
+        {
+            return self.for_in_statement();
+        }
+
+        let initializer = if self.match_types(&[TokenType::Semicolon]) {
+            None
+        } else if self.match_types(&[TokenType::Var]) {
+            Some(self.var_declaration()?)
+        } else {
+            let expr = self.expression()?;
+            self.consume(&TokenType::Semicolon, ParseErrorType::ExpectedSemicolon)?;
+
+            Some(Stmt::Expression(expr))
+        };
+
+        let condition = if self.check(&TokenType::Semicolon) {
+            Expr::Literal(Literal::Bool(true))
+        } else {
+            self.expression()?
+        };
+        self.consume(&TokenType::Semicolon, ParseErrorType::ExpectedSemicolon)?;
+
+        let increment = if self.check(&TokenType::RightParen) {
+            None
+        } else {
+            Some(self.expression()?)
+        };
+        self.consume(&TokenType::RightParen, ParseErrorType::ExpectedRightParen)?;
+
+        let mut body = Stmt::While {
+            condition,
+            body: Box::new(self.statement()?),
+            increment,
+        };
+
+        if let Some(initializer) = initializer {
+            body = Stmt::Block(vec![initializer, body]);
+        }
+
+        Ok(body)
+    }
+
+    /// `for (name in iterable) body`, the opening parenthesis has been
+    /// consumed already.
+    fn for_in_statement(&mut self) -> Result<Stmt, ParseError> {
+        let name = self.advance().clone();
+        self.advance();
+
+        let iterable = self.expression()?;
+        self.consume(&TokenType::RightParen, ParseErrorType::ExpectedRightParen)?;
+
+        Ok(Stmt::ForIn {
+            name,
+            iterable,
+            body: Box::new(self.statement()?),
+        })
+    }
+
+    fn if_statement(&mut self) -> Result<Stmt, ParseError> {
+        self.consume(&TokenType::LeftParen, ParseErrorType::ExpectedLeftParen)?;
+        let condition = self.expression()?;
+        self.consume(&TokenType::RightParen, ParseErrorType::ExpectedRightParen)?;
+
+        let then_branch = Box::new(self.statement()?);
+        // the else is bound to the nearest if, as the inner if consumes it first
+        let else_branch = if self.match_types(&[TokenType::Else]) {
+            Some(Box::new(self.statement()?))
+        } else {
+            None
+        };
+
+        Ok(Stmt::If {
+            condition,
+            then_branch,
+            else_branch,
+        })
+    }
+
+    fn while_statement(&mut self) -> Result<Stmt, ParseError> {
+        self.consume(&TokenType::LeftParen, ParseErrorType::ExpectedLeftParen)?;
+        let condition = self.expression()?;
+        self.consume(&TokenType::RightParen, ParseErrorType::ExpectedRightParen)?;
+
+        let body = Box::new(self.statement()?);
+
+        Ok(Stmt::While {
+            condition,
+            body,
+            increment: None,
+        })
+    }
+
+    fn do_while_statement(&mut self) -> Result<Stmt, ParseError> {
+        let body = Box::new(self.statement()?);
+
+        self.consume(&TokenType::While, ParseErrorType::ExpectedWhile)?;
+        self.consume(&TokenType::LeftParen, ParseErrorType::ExpectedLeftParen)?;
+        let condition = self.expression()?;
+        self.consume(&TokenType::RightParen, ParseErrorType::ExpectedRightParen)?;
+        self.consume(&TokenType::Semicolon, ParseErrorType::ExpectedSemicolon)?;
+
+        Ok(Stmt::DoWhile { body, condition })
+    }
+
+    fn assert_statement(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.previous().clone();
+        let condition = self.expression()?;
+
+        let message = if self.match_types(&[TokenType::Comma]) {
+            Some(self.expression()?)
+        } else {
+            None
+        };
+
+        self.consume(&TokenType::Semicolon, ParseErrorType::ExpectedSemicolon)?;
+
+        Ok(Stmt::Assert {
+            keyword,
+            condition,
+            message,
+        })
+    }
+
+    /// `try { ... } catch (name) { ... }`
+    fn try_statement(&mut self) -> Result<Stmt, ParseError> {
+        self.consume(&TokenType::LeftBrace, ParseErrorType::ExpectedLeftBrace)?;
+        let body = self.block()?;
+
+        self.consume(&TokenType::Catch, ParseErrorType::ExpectedCatch)?;
+        self.consume(&TokenType::LeftParen, ParseErrorType::ExpectedLeftParen)?;
+        let name = self.consume_identifier(ParseErrorType::ExpectedCatchName)?;
+        self.consume(&TokenType::RightParen, ParseErrorType::ExpectedRightParen)?;
+
+        self.consume(&TokenType::LeftBrace, ParseErrorType::ExpectedLeftBrace)?;
+        let handler = self.block()?;
+
+        Ok(Stmt::Try {
+            body,
+            name,
+            handler,
+        })
+    }
+
+    /// `switch (subject) { case value: ... default: ... }`, the default case
+    /// has to be the last one.
+    fn switch_statement(&mut self) -> Result<Stmt, ParseError> {
+        self.consume(&TokenType::LeftParen, ParseErrorType::ExpectedLeftParen)?;
+        let subject = self.expression()?;
+        self.consume(&TokenType::RightParen, ParseErrorType::ExpectedRightParen)?;
+        self.consume(&TokenType::LeftBrace, ParseErrorType::ExpectedLeftBrace)?;
+
+        let mut cases = Vec::new();
+        let mut default = None;
+        while !self.match_types(&[TokenType::RightBrace]) {
+            if default.is_none() && self.match_types(&[TokenType::Case]) {
+                let value = self.expression()?;
+                self.consume(&TokenType::Colon, ParseErrorType::ExpectedCaseColon)?;
+
+                cases.push((value, self.case_body()?));
+            } else if default.is_none() && self.match_types(&[TokenType::Default]) {
+                self.consume(&TokenType::Colon, ParseErrorType::ExpectedCaseColon)?;
+
+                default = Some(self.case_body()?);
+            } else {
+                return Err(self.error(ParseErrorType::ExpectedCase));
+            }
+        }
+
+        Ok(Stmt::Switch {
+            subject,
+            cases,
+            default,
+        })
+    }
+
+    /// Statements up to the next case or the end of the switch.
+    fn case_body(&mut self) -> Result<Vec<Stmt>, ParseError> {
+        let mut statements = Vec::new();
+
+        while !self.check(&TokenType::Case)
+            && !self.check(&TokenType::Default)
+            && !self.check(&TokenType::RightBrace)
+            && !self.is_at_end()
+        {
+            statements.push(self.declaration()?);
+        }
+
+        Ok(statements)
+    }
+
+    fn return_statement(&mut self) -> Result<Stmt, ParseError> {
+        let keyword = self.previous().clone();
+
+        let value = if self.check(&TokenType::Semicolon) {
+            None
+        } else {
+            Some(self.expression()?)
+        };
+
+        self.consume(&TokenType::Semicolon, ParseErrorType::ExpectedSemicolon)?;
+
+        Ok(Stmt::Return { keyword, value })
+    }
+
+    fn block(&mut self) -> Result<Vec<Stmt>, ParseError> {
+        let mut statements = Vec::new();
+
+        while !self.check(&TokenType::RightBrace) && !self.is_at_end() {
+            statements.push(self.declaration()?);
+        }
+
+        self.consume(&TokenType::RightBrace, ParseErrorType::ExpectedRightBrace)?;
+
+        Ok(statements)
+    }
+
+    fn expression(&mut self) -> Result<Expr, ParseError> {
+        self.assignment()
+    }
+
+    fn assignment(&mut self) -> Result<Expr, ParseError> {
+        let expr = self.conditional()?;
+
+        if self.match_types(&[TokenType::Equal]) {
+            let equals = self.previous().clone();
+            let value = self.assignment()?;
+
+            return match expr {
+                Expr::Variable { name, .. } => Ok(Expr::Assign {
+                    id: next_expr_id(),
+                    name,
+                    value: Box::new(value),
+                }),
+                Expr::Get { object, name } => Ok(Expr::Set {
+                    object,
+                    name,
+                    value: Box::new(value),
+                    operator: None,
+                }),
+                Expr::Index {
+                    object,
+                    bracket,
+                    index,
+                } => Ok(Expr::SetIndex {
+                    object,
+                    bracket,
+                    index,
+                    value: Box::new(value),
+                    operator: None,
+                }),
+                _ => Err(ParseError::new(
+                    ParseErrorType::InvalidAssignmentTarget,
+                    &equals,
+                )),
+            };
+        }
+
+        if self.match_types(&[
+            TokenType::PlusEqual,
+            TokenType::MinusEqual,
+            TokenType::StarEqual,
+            TokenType::SlashEqual,
+        ]) {
+            let operator = self.previous().clone();
+            let value = self.assignment()?;
+
+            return compound_assignment(expr, &operator, value).ok_or_else(|| {
+                ParseError::new(ParseErrorType::InvalidAssignmentTarget, &operator)
+            });
+        }
+
+        Ok(expr)
+    }
+
+    /// `condition ? then : else`, right-associative so that chains read like
+    /// `if`/`else if`.
+    fn conditional(&mut self) -> Result<Expr, ParseError> {
+        let condition = self.or()?;
+
+        if !self.match_types(&[TokenType::Question]) {
+            return Ok(condition);
+        }
+
+        let then_branch = self.expression()?;
+        self.consume(&TokenType::Colon, ParseErrorType::ExpectedColon)?;
+        let else_branch = self.conditional()?;
+
+        Ok(Expr::Conditional {
+            condition: Box::new(condition),
+            then_branch: Box::new(then_branch),
+            else_branch: Box::new(else_branch),
+        })
+    }
+
+    fn or(&mut self) -> Result<Expr, ParseError> {
+        self.logical(Self::and, &TokenType::Or)
+    }
+
+    fn and(&mut self) -> Result<Expr, ParseError> {
+        self.logical(Self::equality, &TokenType::And)
+    }
+
+    /// Same as `binary`, but produces the short-circuiting `Logical` node.
+    fn logical(
+        &mut self,
+        operand: fn(&mut Self) -> Result<Expr, ParseError>,
+        operator: &TokenType,
+    ) -> Result<Expr, ParseError> {
+        let mut expr = operand(self)?;
+
+        while self.match_types(std::slice::from_ref(operator)) {
+            let operator = self.previous().clone();
+            let right = operand(self)?;
+            expr = Expr::Logical {
+                left: Box::new(expr),
+                operator,
+                right: Box::new(right),
+            };
+        }
+
+        Ok(expr)
+    }
+
+    fn equality(&mut self) -> Result<Expr, ParseError> {
+        self.binary(
+            Self::comparison,
+            &[TokenType::BangEqual, TokenType::EqualEqual],
+        )
+    }
+
+    /// `1 < x < 3` would compare the boolean `1 < x` to 3, so comparisons
+    /// don't chain without parentheses.
+    fn comparison(&mut self) -> Result<Expr, ParseError> {
+        const OPERATORS: &[TokenType] = &[
+            TokenType::Greater,
+            TokenType::GreaterEqual,
+            TokenType::Less,
+            TokenType::LessEqual,
+        ];
+
+        let expr = self.bitwise_or()?;
+
+        if !self.match_types(OPERATORS) {
+            return Ok(expr);
+        }
+
+        let operator = self.previous().clone();
+        let right = self.bitwise_or()?;
+
+        if self.match_types(OPERATORS) {
+            return Err(ParseError::new(
+                ParseErrorType::ChainedComparison,
+                self.previous(),
+            ));
+        }
+
+        Ok(Expr::Binary {
+            left: Box::new(expr),
+            operator,
+            right: Box::new(right),
+        })
+    }
+
+    // the bitwise operators bind tighter than comparisons, unlike in C, so
+    // that `flags & mask == mask` needs no parentheses
+    fn bitwise_or(&mut self) -> Result<Expr, ParseError> {
+        self.binary(Self::bitwise_xor, &[TokenType::Pipe])
+    }
+
+    fn bitwise_xor(&mut self) -> Result<Expr, ParseError> {
+        self.binary(Self::bitwise_and, &[TokenType::Caret])
+    }
+
+    fn bitwise_and(&mut self) -> Result<Expr, ParseError> {
+        self.binary(Self::shift, &[TokenType::Ampersand])
+    }
+
+    fn shift(&mut self) -> Result<Expr, ParseError> {
+        self.binary(
+            Self::term,
+            &[TokenType::LessLess, TokenType::GreaterGreater],
+        )
+    }
+
+    fn term(&mut self) -> Result<Expr, ParseError> {
+        self.binary(Self::factor, &[TokenType::Minus, TokenType::Plus])
+    }
+
+    fn factor(&mut self) -> Result<Expr, ParseError> {
+        self.binary(
+            Self::unary,
+            &[
+                TokenType::Slash,
+                TokenType::Star,
+                TokenType::Percent,
+                TokenType::TildeSlash,
+            ],
+        )
+    }
+
+    /// Parses a left-associative chain of `operand (operator operand)*`.
+    fn binary(
+        &mut self,
+        operand: fn(&mut Self) -> Result<Expr, ParseError>,
+        operators: &[TokenType],
+    ) -> Result<Expr, ParseError> {
+        let mut expr = operand(self)?;
+
+        while self.match_types(operators) {
+            let operator = self.previous().clone();
+            let right = operand(self)?;
+            expr = Expr::Binary {
+                left: Box::new(expr),
+                operator,
+                right: Box::new(right),
+            };
+        }
+
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> Result<Expr, ParseError> {
+        if self.match_types(&[TokenType::PlusPlus, TokenType::MinusMinus]) {
+            let operator = self.previous().clone();
+
+            let target @ Expr::Variable { .. } = self.unary()? else {
+                return Err(ParseError::new(
+                    ParseErrorType::InvalidIncrementTarget,
+                    &operator,
+                ));
+            };
+
+            let one = Expr::Literal(Literal::Int(1));
+
+            return Ok(compound_assignment(target, &operator, one)
+                .expect("a variable is an assignment target"));
+        }
+
+        if self.match_types(&[TokenType::Bang, TokenType::Minus, TokenType::TypeOf]) {
+            let operator = self.previous().clone();
+            let right = self.unary()?;
+
+            return Ok(Expr::Unary {
+                operator,
+                right: Box::new(right),
+            });
+        }
+
+        self.postfix()
+    }
+
+    /// Postfix operators bind tighter than the prefix ones, so `-3!` is
+    /// `-(3!)`.
+    fn postfix(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.call()?;
+
+        while self.match_types(&[TokenType::Bang]) {
+            expr = Expr::Postfix {
+                left: Box::new(expr),
+                operator: self.previous().clone(),
+            };
+        }
+
+        Ok(expr)
+    }
+
+    fn call(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.primary()?;
+
+        loop {
+            if self.match_types(&[TokenType::Dot]) {
+                let name = self.consume_identifier(ParseErrorType::ExpectedPropertyName)?;
+
+                expr = Expr::Get {
+                    object: Box::new(expr),
+                    name,
+                };
+                continue;
+            }
+
+            if self.match_types(&[TokenType::LeftBracket]) {
+                let index = self.expression()?;
+                let bracket = self
+                    .consume(
+                        &TokenType::RightBracket,
+                        ParseErrorType::ExpectedRightBracket,
+                    )?
+                    .clone();
+
+                expr = Expr::Index {
+                    object: Box::new(expr),
+                    bracket,
+                    index: Box::new(index),
+                };
+                continue;
+            }
+
+            if !self.match_types(&[TokenType::LeftParen]) {
+                break;
+            }
+
+            let arguments = self.arguments(&TokenType::RightParen)?;
+
+            let paren = self
+                .consume(&TokenType::RightParen, ParseErrorType::ExpectedRightParen)?
+                .clone();
+
+            expr = Expr::Call {
+                callee: Box::new(expr),
+                paren,
+                arguments,
+            };
+        }
+
+        Ok(expr)
+    }
+
+    /// Parses comma separated expressions up to the closing token, which is
+    /// left to the caller.
+    fn arguments(&mut self, closing: &TokenType) -> Result<Vec<Expr>, ParseError> {
+        let mut arguments = Vec::new();
+
+        if !self.check(closing) {
+            loop {
+                arguments.push(self.expression()?);
+
+                if !self.match_types(&[TokenType::Comma]) {
+                    break;
+                }
+            }
+        }
+
+        Ok(arguments)
+    }
+
+    fn primary(&mut self) -> Result<Expr, ParseError> {
+        let literal = match self.peek().token_type() {
+            TokenType::False => Literal::Bool(false),
+            TokenType::True => Literal::Bool(true),
+            TokenType::Nil => Literal::Nil,
+            TokenType::Int(integer) => Literal::Int(*integer),
+            TokenType::Number(number) => Literal::Number(*number),
+            TokenType::String(string) => Literal::String(string.clone()),
+            TokenType::Interpolation(parts) => {
+                let parts = parts.clone();
+                self.advance();
+
+                let parts = parts
+                    .into_iter()
+                    .filter(
+                        |part| !matches!(part, StringPart::Literal(string) if string.is_empty()),
+                    )
+                    .map(|part| match part {
+                        StringPart::Literal(string) => Ok(Expr::Literal(Literal::String(string))),
+                        StringPart::Expression(tokens) => Parser::new(tokens).parse_expression(),
+                    })
+                    .collect::<Result<_, _>>()?;
+
+                return Ok(Expr::Interpolation(parts));
+            }
+            TokenType::Fun => {
+                let keyword = self.advance().clone();
+                // lambdas have an empty name
+                let name = Token::new(
+                    TokenType::Identifier(String::new()),
+                    *keyword.span(),
+                    keyword.column(),
+                );
+
+                return Ok(Expr::Lambda(Rc::new(self.function_body(name)?)));
+            }
+            TokenType::This => {
+                let keyword = self.advance().clone();
+
+                return Ok(Expr::This {
+                    id: next_expr_id(),
+                    keyword,
+                });
+            }
+            TokenType::Super => {
+                let keyword = self.advance().clone();
+                self.consume(&TokenType::Dot, ParseErrorType::ExpectedDot)?;
+                let method = self.consume_identifier(ParseErrorType::ExpectedPropertyName)?;
+
+                return Ok(Expr::Super {
+                    id: next_expr_id(),
+                    keyword,
+                    method,
+                });
+            }
+            TokenType::Identifier(_) => {
+                let name = self.advance().clone();
+
+                return Ok(Expr::Variable {
+                    id: next_expr_id(),
+                    name,
+                });
+            }
+            TokenType::LeftBracket => {
+                self.advance();
+                let elements = self.arguments(&TokenType::RightBracket)?;
+                let bracket = self
+                    .consume(
+                        &TokenType::RightBracket,
+                        ParseErrorType::ExpectedRightBracket,
+                    )?
+                    .clone();
+
+                return Ok(Expr::List { bracket, elements });
+            }
+            TokenType::LeftBrace => {
+                let brace = self.advance().clone();
+
+                let mut entries = Vec::new();
+                if !self.check(&TokenType::RightBrace) {
+                    loop {
+                        let key = self.expression()?;
+                        self.consume(&TokenType::Colon, ParseErrorType::ExpectedMapColon)?;
+                        entries.push((key, self.expression()?));
+
+                        if !self.match_types(&[TokenType::Comma]) {
+                            break;
+                        }
+                    }
+                }
+
+                self.consume(&TokenType::RightBrace, ParseErrorType::ExpectedRightBrace)?;
+
+                return Ok(Expr::Map { brace, entries });
+            }
+            TokenType::LeftParen => {
+                self.advance();
+                let expr = self.expression()?;
+                self.consume(&TokenType::RightParen, ParseErrorType::ExpectedRightParen)?;
+
+                return Ok(Expr::Grouping(Box::new(expr)));
+            }
+            _ => return Err(self.error(ParseErrorType::ExpectedExpression)),
+        };
+
+        self.advance();
+
+        Ok(Expr::Literal(literal))
+    }
+
+    fn match_types(&mut self, types: &[TokenType]) -> bool {
+        if types.iter().any(|t| self.check(t)) {
+            self.advance();
+            return true;
+        }
+
+        false
+    }
+
+    fn consume(&mut self, t: &TokenType, error: ParseErrorType) -> Result<&Token, ParseError> {
+        if self.check(t) {
+            return Ok(self.advance());
+        }
+
+        Err(self.error(error))
+    }
+
+    fn consume_identifier(&mut self, error: ParseErrorType) -> Result<Token, ParseError> {
+        self.consume(&TokenType::Identifier(String::new()), error)
+            .cloned()
+    }
+
+    fn check(&self, t: &TokenType) -> bool {
+        !self.is_at_end() && self.peek().token_type().same_kind(t)
+    }
+
+    /// Same as `check`, for the token `distance` tokens after the current one.
+    fn check_ahead(&self, distance: usize, t: &TokenType) -> bool {
+        self.tokens
+            .get(self.current + distance)
+            .is_some_and(|token| token.token_type().same_kind(t))
+    }
+
+    fn advance(&mut self) -> &Token {
+        if !self.is_at_end() {
+            self.current += 1;
+        }
+
+        self.previous()
+    }
+
+    fn is_at_end(&self) -> bool {
+        matches!(self.peek().token_type(), TokenType::Eof)
+    }
+
+    fn peek(&self) -> &Token {
+        &self.tokens[self.current]
+    }
+
+    fn previous(&self) -> &Token {
+        &self.tokens[self.current - 1]
+    }
+
+    /// Discards tokens until the beginning of the next statement.
+    fn synchronize(&mut self) {
+        self.advance();
+
+        while !self.is_at_end() {
+            if let TokenType::Semicolon = self.previous().token_type() {
+                return;
+            }
+
+            if let TokenType::Class
+            | TokenType::Fun
+            | TokenType::Var
+            | TokenType::For
+            | TokenType::If
+            | TokenType::While
+            | TokenType::Do
+            | TokenType::Switch
+            | TokenType::Try
+            | TokenType::Throw
+            | TokenType::Include
+            | TokenType::Print
+            | TokenType::Assert
+            | TokenType::Return
+            | TokenType::Break
+            | TokenType::Continue = self.peek().token_type()
+            {
+                return;
+            }
+
+            self.advance();
+        }
+    }
+
+    fn error(&self, t: ParseErrorType) -> ParseError {
+        ParseError::new(t, self.peek())
+    }
+}
@@ -0,0 +1,509 @@
+use std::{collections::HashMap, fmt};
+
+use crate::{
+    interpreter::Interpreter,
+    lexer::Token,
+    parser::{Expr, ExprId, Function, Stmt},
+};
+
+#[derive(Debug)]
+pub enum ResolveErrorType {
+    ReadInOwnInitializer,
+    AlreadyDeclared,
+    ReturnOutsideFunction,
+    ReturnValueFromInitializer,
+    BreakOutsideLoop,
+    ContinueOutsideLoop,
+    ThisOutsideClass,
+    InheritFromSelf,
+    SuperOutsideClass,
+    SuperWithoutSuperclass,
+    ThisInStaticMethod,
+    SuperInStaticMethod,
+}
+
+#[derive(Debug)]
+pub struct ResolveError {
+    t: ResolveErrorType,
+    line: usize,
+}
+
+impl ResolveError {
+    pub fn new(t: ResolveErrorType, line: usize) -> Self {
+        Self { t, line }
+    }
+
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    pub fn error_type(&self) -> &ResolveErrorType {
+        &self.t
+    }
+}
+
+impl fmt::Display for ResolveErrorType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = match self {
+            ResolveErrorType::ReadInOwnInitializer => {
+                "Can't read local variable in its own initializer."
+            }
+            ResolveErrorType::AlreadyDeclared => "Already a variable with this name in this scope.",
+            ResolveErrorType::ReturnOutsideFunction => "Can't return from top-level code.",
+            ResolveErrorType::ReturnValueFromInitializer => {
+                "Can't return a value from an initializer."
+            }
+            ResolveErrorType::BreakOutsideLoop => "Can't use 'break' outside of a loop.",
+            ResolveErrorType::ContinueOutsideLoop => "Can't use 'continue' outside of a loop.",
+            ResolveErrorType::ThisOutsideClass => "Can't use 'this' outside of a class.",
+            ResolveErrorType::InheritFromSelf => "A class can't inherit from itself.",
+            ResolveErrorType::SuperOutsideClass => "Can't use 'super' outside of a class.",
+            ResolveErrorType::ThisInStaticMethod => "Can't use 'this' in a static method.",
+            ResolveErrorType::SuperInStaticMethod => "Can't use 'super' in a static method.",
+            ResolveErrorType::SuperWithoutSuperclass => {
+                "Can't use 'super' in a class with no superclass."
+            }
+        };
+
+        write!(f, "{message}")
+    }
+}
+
+impl fmt::Display for ResolveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[line {}] Error: {}", self.line, self.t)
+    }
+}
+
+impl std::error::Error for ResolveError {}
+
+#[derive(Clone, Copy, PartialEq)]
+enum FunctionType {
+    None,
+    Function,
+    Method,
+    Initializer,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ClassType {
+    None,
+    Class,
+    Subclass,
+    // static methods are inside the class, but have no instance to refer to
+    Static,
+}
+
+/// Static pass over the program that tells the interpreter how many scopes
+/// away each local variable was declared, variables that aren't found in any
+/// scope are assumed to be globals.
+pub struct Resolver<'a> {
+    interpreter: &'a mut Interpreter,
+    // whether each variable of a scope has been fully defined yet
+    scopes: Vec<HashMap<String, bool>>,
+    current_function: FunctionType,
+    current_class: ClassType,
+    // number of loops around the current statement, within its function
+    loop_depth: usize,
+    errors: Vec<ResolveError>,
+}
+
+impl<'a> Resolver<'a> {
+    pub fn new(interpreter: &'a mut Interpreter) -> Self {
+        Self {
+            interpreter,
+            scopes: Vec::new(),
+            current_function: FunctionType::None,
+            current_class: ClassType::None,
+            loop_depth: 0,
+            errors: Vec::new(),
+        }
+    }
+
+    pub fn resolve(mut self, statements: &[Stmt]) -> Result<(), Vec<ResolveError>> {
+        self.resolve_statements(statements);
+
+        self.finish()
+    }
+
+    /// Same as `resolve`, for the bare expressions typed in the promt.
+    pub fn resolve_expression(mut self, expr: &Expr) -> Result<(), Vec<ResolveError>> {
+        self.resolve_expr(expr);
+
+        self.finish()
+    }
+
+    fn finish(self) -> Result<(), Vec<ResolveError>> {
+        if !self.errors.is_empty() {
+            return Err(self.errors);
+        }
+
+        Ok(())
+    }
+
+    fn resolve_statements(&mut self, statements: &[Stmt]) {
+        for stmt in statements {
+            self.resolve_stmt(stmt);
+        }
+    }
+
+    fn resolve_block(&mut self, statements: &[Stmt]) {
+        self.begin_scope();
+        self.resolve_statements(statements);
+        self.end_scope();
+    }
+
+    fn resolve_stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Expression(expr) | Stmt::Print(expr) => self.resolve_expr(expr),
+            Stmt::Assert {
+                condition, message, ..
+            } => {
+                self.resolve_expr(condition);
+                if let Some(message) = message {
+                    self.resolve_expr(message);
+                }
+            }
+            Stmt::Var { name, initializer } => {
+                self.declare(name);
+                if let Some(initializer) = initializer {
+                    self.resolve_expr(initializer);
+                }
+                self.define(name);
+            }
+            Stmt::Block(statements) => self.resolve_block(statements),
+            Stmt::If {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                self.resolve_expr(condition);
+                self.resolve_stmt(then_branch);
+                if let Some(else_branch) = else_branch {
+                    self.resolve_stmt(else_branch);
+                }
+            }
+            Stmt::While {
+                condition,
+                body,
+                increment,
+            } => {
+                self.resolve_expr(condition);
+
+                self.loop_depth += 1;
+                self.resolve_stmt(body);
+                self.loop_depth -= 1;
+
+                if let Some(increment) = increment {
+                    self.resolve_expr(increment);
+                }
+            }
+            Stmt::DoWhile { body, condition } => {
+                self.loop_depth += 1;
+                self.resolve_stmt(body);
+                self.loop_depth -= 1;
+
+                self.resolve_expr(condition);
+            }
+            Stmt::ForIn {
+                name,
+                iterable,
+                body,
+            } => {
+                self.resolve_expr(iterable);
+
+                // the name is defined in a scope of its own, around the body
+                self.begin_scope();
+                self.declare(name);
+                self.define(name);
+
+                self.loop_depth += 1;
+                self.resolve_stmt(body);
+                self.loop_depth -= 1;
+
+                self.end_scope();
+            }
+            Stmt::Switch {
+                subject,
+                cases,
+                default,
+            } => {
+                self.resolve_expr(subject);
+                for (value, body) in cases {
+                    self.resolve_expr(value);
+                    self.resolve_block(body);
+                }
+                if let Some(default) = default {
+                    self.resolve_block(default);
+                }
+            }
+            Stmt::Throw { value, .. } => self.resolve_expr(value),
+            Stmt::Try {
+                body,
+                name,
+                handler,
+            } => {
+                self.resolve_block(body);
+
+                self.begin_scope();
+                self.declare(name);
+                self.define(name);
+                self.resolve_statements(handler);
+                self.end_scope();
+            }
+            Stmt::Break(keyword) => {
+                if self.loop_depth == 0 {
+                    self.error(ResolveErrorType::BreakOutsideLoop, keyword);
+                }
+            }
+            Stmt::Continue(keyword) => {
+                if self.loop_depth == 0 {
+                    self.error(ResolveErrorType::ContinueOutsideLoop, keyword);
+                }
+            }
+            Stmt::Function(function) => {
+                // defined eagerly, so that the function can refer to itself
+                self.declare(&function.name);
+                self.define(&function.name);
+
+                self.resolve_function(function, FunctionType::Function);
+            }
+            Stmt::Class {
+                name,
+                superclass,
+                methods,
+                static_methods,
+            } => {
+                let enclosing_class = self.current_class;
+
+                self.declare(name);
+                self.define(name);
+
+                // they close over the scope of the class, without `super` and `this`
+                self.current_class = ClassType::Static;
+                for method in static_methods {
+                    self.resolve_function(method, FunctionType::Method);
+                }
+
+                self.current_class = ClassType::Class;
+
+                if let Some(superclass) = superclass {
+                    let is_self = matches!(
+                        superclass,
+                        Expr::Variable { name: superclass, .. } if superclass.name() == name.name()
+                    );
+                    if is_self {
+                        self.error(ResolveErrorType::InheritFromSelf, name);
+                    }
+
+                    self.current_class = ClassType::Subclass;
+                    self.resolve_expr(superclass);
+
+                    self.begin_scope();
+                    self.define_name("super");
+                }
+
+                // the scope that the bound methods define `this` in
+                self.begin_scope();
+                self.define_name("this");
+
+                for method in methods {
+                    let function_type = if method.name.name() == "init" {
+                        FunctionType::Initializer
+                    } else {
+                        FunctionType::Method
+                    };
+
+                    self.resolve_function(method, function_type);
+                }
+
+                self.end_scope();
+
+                if superclass.is_some() {
+                    self.end_scope();
+                }
+
+                self.current_class = enclosing_class;
+            }
+            // expanded by `Lox` before the program is resolved
+            Stmt::Include { .. } => {}
+            Stmt::Return { keyword, value } => {
+                if self.current_function == FunctionType::None {
+                    self.error(ResolveErrorType::ReturnOutsideFunction, keyword);
+                }
+
+                if let Some(value) = value {
+                    if self.current_function == FunctionType::Initializer {
+                        self.error(ResolveErrorType::ReturnValueFromInitializer, keyword);
+                    }
+
+                    self.resolve_expr(value);
+                }
+            }
+        }
+    }
+
+    fn resolve_function(&mut self, function: &Function, function_type: FunctionType) {
+        // the defaults are evaluated in the closure, not in the function
+        for default in &function.defaults {
+            self.resolve_expr(default);
+        }
+
+        let enclosing_function = self.current_function;
+        self.current_function = function_type;
+        // loops don't reach into the functions declared in them
+        let enclosing_loop_depth = std::mem::take(&mut self.loop_depth);
+
+        self.begin_scope();
+        for param in function.params.iter().chain(&function.rest) {
+            self.declare(param);
+            self.define(param);
+        }
+        self.resolve_statements(&function.body);
+        self.end_scope();
+
+        self.current_function = enclosing_function;
+        self.loop_depth = enclosing_loop_depth;
+    }
+
+    fn resolve_expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Binary { left, right, .. } | Expr::Logical { left, right, .. } => {
+                self.resolve_expr(left);
+                self.resolve_expr(right);
+            }
+            Expr::Unary { right, .. } | Expr::Postfix { left: right, .. } => {
+                self.resolve_expr(right);
+            }
+            Expr::Literal(_) => {}
+            Expr::Grouping(expr) => self.resolve_expr(expr),
+            Expr::Variable { id, name } => {
+                let is_declared_only = self
+                    .scopes
+                    .last()
+                    .and_then(|scope| scope.get(name.name()))
+                    .is_some_and(|is_defined| !is_defined);
+
+                if is_declared_only {
+                    self.error(ResolveErrorType::ReadInOwnInitializer, name);
+                }
+
+                self.resolve_local(*id, name);
+            }
+            Expr::Assign { id, name, value } => {
+                self.resolve_expr(value);
+                self.resolve_local(*id, name);
+            }
+            Expr::Call {
+                callee, arguments, ..
+            } => {
+                self.resolve_expr(callee);
+                for argument in arguments {
+                    self.resolve_expr(argument);
+                }
+            }
+            Expr::Interpolation(parts) => {
+                for part in parts {
+                    self.resolve_expr(part);
+                }
+            }
+            Expr::Lambda(function) => self.resolve_function(function, FunctionType::Function),
+            Expr::Conditional {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                self.resolve_expr(condition);
+                self.resolve_expr(then_branch);
+                self.resolve_expr(else_branch);
+            }
+            Expr::List { elements, .. } => {
+                for element in elements {
+                    self.resolve_expr(element);
+                }
+            }
+            Expr::Map { entries, .. } => {
+                for (key, value) in entries {
+                    self.resolve_expr(key);
+                    self.resolve_expr(value);
+                }
+            }
+            Expr::Index { object, index, .. } => {
+                self.resolve_expr(object);
+                self.resolve_expr(index);
+            }
+            Expr::SetIndex {
+                object,
+                index,
+                value,
+                ..
+            } => {
+                self.resolve_expr(object);
+                self.resolve_expr(index);
+                self.resolve_expr(value);
+            }
+            Expr::Get { object, .. } => self.resolve_expr(object),
+            Expr::Set { object, value, .. } => {
+                self.resolve_expr(value);
+                self.resolve_expr(object);
+            }
+            Expr::Super { id, keyword, .. } => match self.current_class {
+                ClassType::None => self.error(ResolveErrorType::SuperOutsideClass, keyword),
+                ClassType::Class => {
+                    self.error(ResolveErrorType::SuperWithoutSuperclass, keyword);
+                }
+                ClassType::Subclass => self.resolve_local(*id, keyword),
+                ClassType::Static => self.error(ResolveErrorType::SuperInStaticMethod, keyword),
+            },
+            Expr::This { id, keyword } => match self.current_class {
+                ClassType::None => self.error(ResolveErrorType::ThisOutsideClass, keyword),
+                ClassType::Static => self.error(ResolveErrorType::ThisInStaticMethod, keyword),
+                ClassType::Class | ClassType::Subclass => self.resolve_local(*id, keyword),
+            },
+        }
+    }
+
+    fn resolve_local(&mut self, id: ExprId, name: &Token) {
+        let depth = self
+            .scopes
+            .iter()
+            .rev()
+            .position(|scope| scope.contains_key(name.name()));
+
+        if let Some(depth) = depth {
+            self.interpreter.resolve(id, depth);
+        }
+    }
+
+    fn begin_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    fn end_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    fn declare(&mut self, name: &Token) {
+        let Some(scope) = self.scopes.last_mut() else {
+            return;
+        };
+
+        if scope.insert(name.name().to_owned(), false).is_some() {
+            self.error(ResolveErrorType::AlreadyDeclared, name);
+        }
+    }
+
+    fn define(&mut self, name: &Token) {
+        self.define_name(name.name());
+    }
+
+    fn define_name(&mut self, name: &str) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_owned(), true);
+        }
+    }
+
+    fn error(&mut self, t: ResolveErrorType, token: &Token) {
+        self.errors.push(ResolveError::new(t, token.span().line));
+    }
+}
@@ -0,0 +1,355 @@
+use std::{
+    env, fs,
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Output, Stdio},
+};
+
+/// Directory of its own for the scripts of a test, so that tests running in
+/// parallel don't overwrite each other's files.
+fn scratch(test: &str) -> PathBuf {
+    let directory = env::temp_dir().join(format!("rlox-cli-{}-{test}", std::process::id()));
+    fs::create_dir_all(&directory).unwrap();
+
+    directory
+}
+
+fn script(directory: &Path, name: &str, source: &str) -> String {
+    let path = directory.join(name);
+    fs::write(&path, source).unwrap();
+
+    path.to_string_lossy().into_owned()
+}
+
+/// Runs the binary with `input` piped through stdin, errors are never colored
+/// so that they can be compared.
+fn rlox(args: &[&str], input: &str, home: &Path) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rlox"))
+        .args(args)
+        .env("HOME", home)
+        .env("NO_COLOR", "1")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+
+    child.wait_with_output().unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8(output.stderr.clone()).unwrap()
+}
+
+#[test]
+fn runs_the_script_file() {
+    let directory = scratch("run");
+    let path = script(&directory, "main.lox", "print 1 + 2;\n");
+
+    let output = rlox(&[&path], "", &directory);
+
+    assert_eq!(stdout(&output), "3\n");
+    assert_eq!(output.status.code(), Some(0));
+}
+
+#[test]
+fn tokens_mode() {
+    let directory = scratch("tokens");
+    let path = script(&directory, "main.lox", "print 1 + 2;\n");
+
+    let output = rlox(&["--tokens", &path], "", &directory);
+
+    assert_eq!(
+        stdout(&output),
+        "1:1 Print 'print'\n1:7 Int(1) '1'\n1:9 Plus '+'\n1:11 Int(2) '2'\n1:12 Semicolon ';'\n2:1 Eof ''\n"
+    );
+}
+
+#[test]
+fn wrong_command_line_prints_the_usage() {
+    let directory = scratch("usage");
+
+    for args in [
+        &["--bogus"][..],
+        &["a.lox", "b.lox"],
+        &["--precision=x", "a.lox"],
+    ] {
+        let output = rlox(args, "", &directory);
+
+        assert!(stderr(&output).starts_with("Usage: rlox "), "{args:?}");
+        assert_eq!(stdout(&output), "");
+        assert_eq!(output.status.code(), Some(64));
+    }
+}
+
+#[test]
+fn ast_mode_prints_the_tree_as_written() {
+    let directory = scratch("ast");
+    let path = script(&directory, "main.lox", "print 1 + 2;\nprint (3);\n");
+
+    let output = rlox(&["--ast", &path], "", &directory);
+
+    assert_eq!(stdout(&output), "(print (+ 1 2))\n(print (group 3))\n");
+}
+
+#[test]
+fn promt_runs_when_there_is_no_script() {
+    let directory = scratch("promt");
+
+    let output = rlox(&[], "print 1;\n", &directory);
+
+    assert_eq!(stdout(&output), "> 1\n> ");
+    assert_eq!(output.status.code(), Some(0));
+}
+
+#[test]
+fn promt_saves_its_history_in_the_home_directory() {
+    let directory = scratch("promt-history");
+    let _ = fs::remove_file(directory.join(".rlox_history"));
+
+    rlox(&[], "var a = 1;\nprint a;\n", &directory);
+    let output = rlox(&[], "1 + 1\n.history\n", &directory);
+
+    assert_eq!(
+        stdout(&output),
+        "> 2\n>    1  var a = 1;\n   2  print a;\n   3  1 + 1\n> "
+    );
+    assert_eq!(
+        fs::read_to_string(directory.join(".rlox_history")).unwrap(),
+        "var a = 1;\nprint a;\n1 + 1\n"
+    );
+}
+
+#[test]
+fn runs_the_script_piped_through_stdin() {
+    let directory = scratch("stdin");
+
+    let output = rlox(&["-"], "var a = \"piped\";\nprint a;\n", &directory);
+
+    assert_eq!(stdout(&output), "piped\n");
+    assert_eq!(output.status.code(), Some(0));
+}
+
+#[test]
+fn missing_script_exits_with_66() {
+    let directory = scratch("missing");
+    let path = directory.join("missing.lox").to_string_lossy().into_owned();
+
+    let output = rlox(&[&path], "", &directory);
+
+    assert!(stderr(&output).starts_with(&format!("Error: could not read '{path}': ")));
+    assert_eq!(output.status.code(), Some(66));
+}
+
+#[test]
+fn errors_show_the_file_and_underline_the_lexeme() {
+    let directory = scratch("caret");
+    let path = script(&directory, "main.lox", "var a = 1;\nprint a +;\n");
+
+    let output = rlox(&[&path], "", &directory);
+
+    assert_eq!(
+        stderr(&output),
+        format!("{path}:2:10: Error: Expect expression.\n    print a +;\n             ^\n")
+    );
+    assert_eq!(output.status.code(), Some(65));
+}
+
+#[test]
+fn errors_are_not_colored_when_piped() {
+    let directory = scratch("color");
+    let path = script(&directory, "main.lox", "print -nil;\n");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_rlox"))
+        .arg(&path)
+        .env_remove("NO_COLOR")
+        .output()
+        .unwrap();
+
+    assert!(!stderr(&output).contains('\x1b'), "{:?}", stderr(&output));
+}
+
+#[test]
+fn json_tokens_mode() {
+    let directory = scratch("json");
+    let path = script(&directory, "main.lox", "print 1;");
+
+    let output = rlox(&["--emit=json", &path], "", &directory);
+
+    let json = stdout(&output);
+    assert!(json.starts_with("[\n"), "{json}");
+    assert!(
+        json.contains(
+            r#"{"type": "Print", "lexeme": "print", "line": 1, "column": 1, "start": 0, "end": 5}"#
+        ),
+        "{json}"
+    );
+    assert!(json.ends_with("]\n"), "{json}");
+}
+
+#[test]
+fn runtime_errors_exit_with_70() {
+    let directory = scratch("runtime");
+    let path = script(&directory, "main.lox", "print 1;\nprint 1 / 0;\n");
+
+    let output = rlox(&[&path], "", &directory);
+
+    assert_eq!(stdout(&output), "1\n");
+    assert_eq!(
+        stderr(&output),
+        format!("{path}:2: Error: Division by zero.\n")
+    );
+    assert_eq!(output.status.code(), Some(70));
+}
+
+#[test]
+fn trace_logs_the_statements() {
+    let directory = scratch("trace");
+    let path = script(&directory, "main.lox", "var a = 1;\nprint a;\n");
+
+    let output = rlox(&["--trace", &path], "", &directory);
+
+    let trace = stderr(&output);
+    assert!(trace.contains("(var a 1)"), "{trace}");
+    assert!(trace.contains("(print a)"), "{trace}");
+    assert_eq!(stdout(&output), "1\n");
+}
+
+#[test]
+fn check_mode_only_reports_errors() {
+    let directory = scratch("check");
+    let valid = script(&directory, "valid.lox", "print 1;\n");
+    let invalid = script(&directory, "invalid.lox", "print 1;\nprint ;\n");
+
+    let output = rlox(&["--check", &valid], "", &directory);
+    assert_eq!(stdout(&output), "");
+    assert_eq!(output.status.code(), Some(0));
+
+    let output = rlox(&["--check", &invalid], "", &directory);
+    assert_eq!(stdout(&output), "");
+    assert!(stderr(&output).contains("Expect expression."));
+    assert_eq!(output.status.code(), Some(65));
+}
+
+#[test]
+fn dot_mode() {
+    let directory = scratch("dot");
+    let path = script(&directory, "main.lox", "print 1 + 2;\n");
+
+    let output = rlox(&["--emit=dot", &path], "", &directory);
+
+    let dot = stdout(&output);
+    assert!(dot.starts_with("digraph ast {\n"), "{dot}");
+    assert!(dot.contains("n2 [label=\"+\"];"), "{dot}");
+    assert!(dot.contains("n1 -> n2 [label=\"value\"];"), "{dot}");
+}
+
+#[test]
+fn errors_name_the_file_they_are_in() {
+    let directory = scratch("files");
+    let lib = script(
+        &directory,
+        "lib.lox",
+        "var a = 1;\nprint ;\n//#line 10 \"generated.lox\"\nprint ;\n",
+    );
+    let path = script(&directory, "main.lox", "include \"lib.lox\";\nprint a;\n");
+
+    let output = rlox(&["--check", &path], "", &directory);
+
+    let errors = stderr(&output)
+        .lines()
+        .filter(|line| line.contains("Error:"))
+        .map(str::to_owned)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        errors,
+        [
+            format!("{lib}:2:7: Error: Expect expression."),
+            "generated.lox:10:7: Error: Expect expression.".to_owned(),
+        ]
+    );
+    assert_eq!(output.status.code(), Some(65));
+}
+
+#[test]
+fn includes_are_relative_to_the_including_file() {
+    let directory = scratch("include");
+    fs::create_dir_all(directory.join("lib")).unwrap();
+    script(
+        &directory,
+        "lib/greet.lox",
+        "fun greet(name) { print \"hi \" + name; }\n",
+    );
+    let path = script(
+        &directory,
+        "main.lox",
+        "include \"lib/greet.lox\";\ngreet(\"there\");\n",
+    );
+
+    let output = rlox(&[&path], "", &directory);
+
+    assert_eq!(stdout(&output), "hi there\n");
+    assert_eq!(output.status.code(), Some(0));
+}
+
+#[test]
+fn missing_and_cyclic_includes_are_errors() {
+    let directory = scratch("include-errors");
+    let missing = script(&directory, "missing.lox", "include \"nowhere.lox\";\n");
+    script(&directory, "b.lox", "include \"a.lox\";\n");
+    let cyclic = script(&directory, "a.lox", "include \"b.lox\";\n");
+
+    let output = rlox(&[&missing], "", &directory);
+    assert!(stderr(&output).contains("Error:"), "{}", stderr(&output));
+    assert_eq!(output.status.code(), Some(65));
+
+    let output = rlox(&[&cyclic], "", &directory);
+    assert!(stderr(&output).contains("Error:"), "{}", stderr(&output));
+    assert_eq!(output.status.code(), Some(65));
+}
+
+#[test]
+fn precision_rounds_printed_numbers() {
+    let directory = scratch("precision");
+    let path = script(&directory, "main.lox", "print 2 / 3;\nprint 1;\n");
+
+    let output = rlox(&["--precision=2", &path], "", &directory);
+    assert_eq!(stdout(&output), "0.67\n1\n");
+
+    let output = rlox(&[&path], "", &directory);
+    assert_eq!(stdout(&output), "0.6666666666666666\n1\n");
+}
+
+#[test]
+fn time_reports_every_phase() {
+    let directory = scratch("time");
+    let path = script(&directory, "main.lox", "print 1;\n");
+
+    let output = rlox(&["--time", &path], "", &directory);
+
+    let phases = stderr(&output)
+        .lines()
+        .map(|line| line.split_whitespace().next().unwrap().to_owned())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        phases,
+        [
+            "phase=lex",
+            "phase=parse",
+            "phase=resolve",
+            "phase=interpret"
+        ]
+    );
+    assert_eq!(stdout(&output), "1\n");
+}
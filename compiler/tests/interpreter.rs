@@ -0,0 +1,1032 @@
+use std::{
+    io::{self, Write},
+    sync::{Arc, Mutex},
+};
+
+use rlox::{
+    error::LoxError,
+    interpreter::{RuntimeError, RuntimeErrorType},
+    lox::{Lox, Mode},
+    resolver::{ResolveError, ResolveErrorType},
+    run_source,
+};
+
+/// Buffer that stays readable after the `Lox` writing to it took a handle.
+#[derive(Clone, Default)]
+struct Output(Arc<Mutex<Vec<u8>>>);
+
+impl Output {
+    fn text(&self) -> String {
+        String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Runs the source and returns what it printed.
+fn run(source: &str) -> String {
+    let output = Output::default();
+    let mut lox = Lox::with_output(Mode::Run, Box::new(output.clone()));
+
+    if let Err(errors) = lox.run(source, None) {
+        panic!("{source:?} failed: {errors:?}");
+    }
+
+    output.text()
+}
+
+fn runtime_error(source: &str) -> RuntimeError {
+    let mut errors = run_source(source).unwrap_err();
+
+    match errors.pop() {
+        Some(LoxError::Runtime(error)) if errors.is_empty() => error,
+        error => panic!("{source:?} should fail at runtime, got {error:?}"),
+    }
+}
+
+fn resolve_error(source: &str) -> ResolveError {
+    let mut errors = run_source(source).unwrap_err();
+
+    match errors.pop() {
+        Some(LoxError::Resolve(error)) if errors.is_empty() => error,
+        error => panic!("{source:?} should fail to resolve, got {error:?}"),
+    }
+}
+
+/// Keeps the trace of the interpreter, the only test that turns tracing on
+/// reads it.
+struct TraceLogger;
+
+static TRACE: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+impl log::Log for TraceLogger {
+    fn enabled(&self, _: &log::Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::Record) {
+        if record.target() == "rlox::interpreter" {
+            TRACE.lock().unwrap().push(record.args().to_string());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// Runs the source with colored errors or not and returns what it reported.
+fn reported(source: &str, use_color: bool) -> String {
+    let error_output = Output::default();
+    let mut lox = Lox::with_output(Mode::Run, Box::new(Output::default()));
+    lox.set_error_output(Box::new(error_output.clone()));
+    lox.set_color(use_color);
+
+    assert!(lox.run(source, Some("main.lox")).is_err());
+
+    error_output.text()
+}
+
+#[test]
+fn arithmetic() {
+    assert_eq!(run("print (1 + 2) * 3;"), "9\n");
+    assert_eq!(run("print 1 + 2 * 3 - 4 / 2;"), "5\n");
+    assert_eq!(run("print -(2.5 * 2);"), "-5\n");
+    assert_eq!(run("print \"con\" + \"cat\";"), "concat\n");
+}
+
+#[test]
+fn operand_type_errors() {
+    assert!(matches!(
+        runtime_error("print -\"a\";").error_type(),
+        RuntimeErrorType::OperandMustBeNumber
+    ));
+    assert!(matches!(
+        runtime_error("print 1 < \"a\";").error_type(),
+        RuntimeErrorType::OperandsMustBeNumbers
+    ));
+    assert!(matches!(
+        runtime_error("print 1 + \"a\";").error_type(),
+        RuntimeErrorType::OperandsMustBeNumbersOrStrings
+    ));
+}
+
+#[test]
+fn truthiness_and_equality() {
+    assert_eq!(
+        run("print !nil; print !0; print !\"\";"),
+        "true\nfalse\nfalse\n"
+    );
+    assert_eq!(
+        run("print nil == nil; print nil == false;"),
+        "true\nfalse\n"
+    );
+    assert_eq!(run("print 1 == 1.0; print \"a\" == \"a\";"), "true\ntrue\n");
+    assert_eq!(run("print 1 != \"1\";"), "true\n");
+}
+
+#[test]
+fn assignment_in_a_block_updates_the_outer_variable() {
+    assert_eq!(run("var a = 1; { a = 2; } print a;"), "2\n");
+    assert!(matches!(
+        runtime_error("b = 1;").error_type(),
+        RuntimeErrorType::UndefinedVariable(name) if name == "b"
+    ));
+}
+
+#[test]
+fn blocks_shadow_and_restore_variables() {
+    let source = "
+        var a = \"outer\";
+        {
+            var a = \"inner\";
+            print a;
+        }
+        print a;
+    ";
+
+    assert_eq!(run(source), "inner\nouter\n");
+}
+
+#[test]
+fn if_else() {
+    assert_eq!(run("if (true) print 1; else print 2;"), "1\n");
+    assert_eq!(run("if (false) print 1;"), "");
+    assert_eq!(run("if (true) if (false) print 1; else print 2;"), "2\n");
+    assert_eq!(run("if (false) if (true) print 1; else print 2;"), "");
+}
+
+#[test]
+fn while_loop_and_logical_operators() {
+    let source = "
+        var n = 4;
+        var sum = 0;
+        while (n > 0) { sum = sum + n; n = n - 1; }
+        print sum;
+    ";
+    assert_eq!(run(source), "10\n");
+
+    let source = "
+        fun side_effect() { print \"evaluated\"; return true; }
+        print false and side_effect();
+        print true or side_effect();
+        print nil or \"default\";
+        print 1 and 2;
+    ";
+    assert_eq!(run(source), "false\ntrue\ndefault\n2\n");
+}
+
+#[test]
+fn for_loops() {
+    assert_eq!(
+        run("for (var i = 0; i < 3; i = i + 1) print i;"),
+        "0\n1\n2\n"
+    );
+    assert_eq!(
+        run("for (var i = 0;; i = i + 1) { if (i == 2) break; print i; }"),
+        "0\n1\n"
+    );
+    assert_eq!(
+        run("var i = 2; for (; i > 0; i = i - 1) print i;"),
+        "2\n1\n"
+    );
+}
+
+#[test]
+fn functions() {
+    assert_eq!(
+        run("fun add(a, b) { return a + b; } print add(1, 2);"),
+        "3\n"
+    );
+    assert_eq!(run("fun f() {} print f();"), "nil\n");
+    assert_eq!(run("fun f() {} print f;"), "<fn f>\n");
+}
+
+#[test]
+fn arity_is_checked() {
+    assert!(matches!(
+        runtime_error("fun f(a, b) {} f(1);").error_type(),
+        RuntimeErrorType::ArityMismatch {
+            expected: 2,
+            got: 1
+        }
+    ));
+    assert!(matches!(
+        runtime_error("\"text\"();").error_type(),
+        RuntimeErrorType::NotCallable
+    ));
+}
+
+#[test]
+fn return_unwinds_out_of_the_function() {
+    assert_eq!(run("fun f() { return; print 1; } print f();"), "nil\n");
+
+    let source = "
+        fun first_even(limit) {
+            for (var i = 1; i < limit; i = i + 1) {
+                if (i % 2 == 0) return i;
+            }
+        }
+        print first_even(10);
+    ";
+    assert_eq!(run(source), "2\n");
+}
+
+#[test]
+fn return_outside_a_function() {
+    assert!(matches!(
+        resolve_error("return 1;").error_type(),
+        ResolveErrorType::ReturnOutsideFunction
+    ));
+}
+
+#[test]
+fn closures_capture_their_scope() {
+    let source = "
+        fun counter() {
+            var count = 0;
+            fun increment() { count = count + 1; return count; }
+            return increment;
+        }
+        var next = counter();
+        next();
+        print next();
+    ";
+    assert_eq!(run(source), "2\n");
+
+    // resolved statically, a later declaration doesn't change the binding
+    let source = "
+        var a = \"global\";
+        {
+            fun show() { print a; }
+            show();
+            var a = \"block\";
+            show();
+        }
+    ";
+    assert_eq!(run(source), "global\nglobal\n");
+}
+
+#[test]
+fn variable_read_in_its_own_initializer() {
+    assert!(matches!(
+        resolve_error("{ var a = a; }").error_type(),
+        ResolveErrorType::ReadInOwnInitializer
+    ));
+    assert!(matches!(
+        resolve_error("{ var a = 1; var a = 2; }").error_type(),
+        ResolveErrorType::AlreadyDeclared
+    ));
+}
+
+#[test]
+fn classes_fields_and_methods() {
+    let source = "
+        class Adder {
+            add(a, b) { return a + b; }
+        }
+        var adder = Adder();
+        adder.x = 1;
+        print adder.x;
+        adder.x = 2;
+        print adder.x;
+        print adder.add(1, 2);
+        var method = adder.add;
+        print method(3, 4);
+    ";
+    assert_eq!(run(source), "1\n2\n3\n7\n");
+
+    assert!(matches!(
+        runtime_error("class A {} A().missing;").error_type(),
+        RuntimeErrorType::UndefinedProperty(name) if name == "missing"
+    ));
+    assert!(matches!(
+        runtime_error("var a = 1; a.field = 2;").error_type(),
+        RuntimeErrorType::OnlyInstancesHaveFields
+    ));
+}
+
+#[test]
+fn this_is_bound_to_the_instance() {
+    let source = "
+        class Point {
+            sum() { return this.x + this.y; }
+            self() { return this; }
+            later() { fun inner() { return this.x; } return inner; }
+        }
+        var point = Point();
+        point.x = 1;
+        point.y = 2;
+        print point.sum();
+        print point.self().y;
+        var method = point.sum;
+        print method();
+        print point.later()();
+    ";
+    assert_eq!(run(source), "3\n2\n3\n1\n");
+
+    assert!(matches!(
+        resolve_error("print this;").error_type(),
+        ResolveErrorType::ThisOutsideClass
+    ));
+    assert!(matches!(
+        resolve_error("fun f() { return this; }").error_type(),
+        ResolveErrorType::ThisOutsideClass
+    ));
+}
+
+#[test]
+fn inheritance_and_super() {
+    let source = "
+        class A {
+            name() { return \"A\"; }
+            greet() { return \"hello from \" + this.name(); }
+        }
+        class B < A {
+            name() { return \"B\"; }
+            greet() { return super.greet() + \"!\"; }
+        }
+        print A().greet();
+        print B().greet();
+    ";
+    assert_eq!(run(source), "hello from A\nhello from B!\n");
+
+    assert!(matches!(
+        resolve_error("class A < A {}").error_type(),
+        ResolveErrorType::InheritFromSelf
+    ));
+    assert!(matches!(
+        resolve_error("class A { f() { super.f(); } }").error_type(),
+        ResolveErrorType::SuperWithoutSuperclass
+    ));
+    assert!(matches!(
+        runtime_error("var A = 1; class B < A {}").error_type(),
+        RuntimeErrorType::SuperclassMustBeClass
+    ));
+}
+
+#[test]
+fn initializers() {
+    let source = "
+        class Point {
+            init(x, y) { this.x = x; this.y = y; }
+        }
+        var point = Point(1, 2);
+        print point.x + point.y;
+        print point.init(3, 4) == point;
+    ";
+    assert_eq!(run(source), "3\ntrue\n");
+
+    assert!(matches!(
+        runtime_error("class P { init(x) {} } P();").error_type(),
+        RuntimeErrorType::ArityMismatch {
+            expected: 1,
+            got: 0
+        }
+    ));
+    assert!(matches!(
+        resolve_error("class P { init() { return 1; } }").error_type(),
+        ResolveErrorType::ReturnValueFromInitializer
+    ));
+}
+
+#[test]
+fn clock_moves_forward() {
+    assert_eq!(
+        run("var first = clock(); var second = clock(); print second >= first;"),
+        "true\n"
+    );
+}
+
+#[test]
+fn errors_are_colored_when_asked() {
+    assert_eq!(
+        reported("print -nil;", true),
+        "main.lox:1: \x1b[1;31mError\x1b[0m: \x1b[1mOperand must be a number.\x1b[0m\n"
+    );
+    assert_eq!(
+        reported("print -nil;", false),
+        "main.lox:1: Error: Operand must be a number.\n"
+    );
+}
+
+#[test]
+fn all_the_errors_are_returned() {
+    let errors = run_source("print 1 +;\nvar;").unwrap_err();
+
+    assert_eq!(errors.len(), 2);
+    assert_eq!(errors[0].line(), 1);
+    assert_eq!(errors[1].line(), 2);
+}
+
+#[test]
+fn conditional_operator() {
+    assert_eq!(run("print true ? 1 : 2;"), "1\n");
+    assert_eq!(run("print false ? 1 : false ? 2 : 3;"), "3\n");
+    assert_eq!(
+        run("fun f() { print \"evaluated\"; } print true ? 1 : f();"),
+        "1\n"
+    );
+}
+
+#[test]
+fn modulo_keeps_the_sign_of_the_dividend() {
+    assert_eq!(run("print 10 % 3;"), "1\n");
+    assert_eq!(run("print -7 % 3;"), "-1\n");
+    assert_eq!(run("print 1 + 10 % 4 * 2;"), "5\n");
+    assert!(matches!(
+        runtime_error("print \"a\" % 2;").error_type(),
+        RuntimeErrorType::OperandsMustBeNumbers
+    ));
+}
+
+#[test]
+fn compound_assignment_operators() {
+    assert_eq!(run("var x = 6; x += 2; print x;"), "8\n");
+    assert_eq!(run("var x = 6; x -= 2; print x;"), "4\n");
+    assert_eq!(run("var x = 6; x *= 2; print x;"), "12\n");
+    assert_eq!(run("var x = 6; x /= 4; print x;"), "1.5\n");
+}
+
+#[test]
+fn compound_assignment_evaluates_the_target_once() {
+    let source = "
+        class Point {}
+        var point = Point();
+        point.x = 1;
+        var list = [1, 2];
+        var calls = 0;
+        fun counted(value) { calls = calls + 1; return value; }
+        counted(point).x += 2;
+        list[counted(1)] *= 10;
+        print point.x;
+        print list[1];
+        print calls;
+    ";
+
+    assert_eq!(run(source), "3\n20\n2\n");
+}
+
+#[test]
+fn increment_and_decrement() {
+    assert_eq!(run("var x = 1; print ++x; print x;"), "2\n2\n");
+    assert_eq!(run("var x = 1; print --x;"), "0\n");
+}
+
+#[test]
+fn break_and_continue() {
+    let source = "
+        for (var i = 0; i < 5; i = i + 1) {
+            if (i == 1) continue;
+            if (i == 3) break;
+            print i;
+        }
+    ";
+    assert_eq!(run(source), "0\n2\n");
+
+    assert!(matches!(
+        resolve_error("break;").error_type(),
+        ResolveErrorType::BreakOutsideLoop
+    ));
+    assert!(matches!(
+        resolve_error("fun f() { continue; }").error_type(),
+        ResolveErrorType::ContinueOutsideLoop
+    ));
+}
+
+#[test]
+fn lambdas() {
+    assert_eq!(
+        run("var square = fun (x) { return x * x; }; print square(3);"),
+        "9\n"
+    );
+    assert_eq!(
+        run("fun apply(f, x) { return f(x); } print apply(fun (x) { return x + 1; }, 1);"),
+        "2\n"
+    );
+    assert_eq!(run("print fun () {};"), "<fn>\n");
+}
+
+#[test]
+fn strings_count_characters() {
+    assert_eq!(run("print len(\"abc\");"), "3\n");
+    assert_eq!(run("print len(\"héllo\");"), "5\n");
+    assert_eq!(run("print substr(\"héllo\", 1, 3);"), "él\n");
+    assert!(matches!(
+        runtime_error("print substr(\"abc\", 2, 4);").error_type(),
+        RuntimeErrorType::IndexOutOfRange
+    ));
+}
+
+#[test]
+fn string_interpolation() {
+    assert_eq!(
+        run("var name = \"Lox\"; print \"hi ${name}!\";"),
+        "hi Lox!\n"
+    );
+    assert_eq!(run("print \"1 + 1 = ${1 + 1}\";"), "1 + 1 = 2\n");
+    assert_eq!(
+        run("var a = 2; print \"outer ${\"inner ${a}\"}\";"),
+        "outer inner 2\n"
+    );
+}
+
+#[test]
+fn values_display_the_way_lox_prints_them() {
+    assert_eq!(run("print 5.0; print 5.5; print -0.25;"), "5\n5.5\n-0.25\n");
+    assert_eq!(
+        run("print nil; print true; print false;"),
+        "nil\ntrue\nfalse\n"
+    );
+    assert_eq!(run("print \"no quotes\";"), "no quotes\n");
+}
+
+#[test]
+fn run_source_runs_a_whole_program() {
+    assert!(run_source("var a = 1; { var b = a + 1; }").is_ok());
+    assert!(run_source("var a = 1; a();").is_err());
+}
+
+#[test]
+fn runtime_error_reports_its_line() {
+    let error = runtime_error("var a = 1;\nprint a + nil;");
+
+    assert_eq!(error.line(), 2);
+    assert_eq!(
+        error.to_string(),
+        "[line 2] Error: Operands must be two numbers or two strings."
+    );
+}
+
+#[test]
+fn lox_error_messages() {
+    let errors = run_source("var a = 1;\nprint a +;").unwrap_err();
+
+    match errors.as_slice() {
+        [error @ LoxError::Parse(_)] => {
+            assert_eq!(error.line(), 2);
+            assert_eq!(error.column(), Some(10));
+            assert_eq!(error.to_string(), "[line 2] Error: Expect expression.");
+            assert_eq!(
+                error.located(Some("main.lox")),
+                "main.lox:2:10: Error: Expect expression."
+            );
+        }
+        errors => panic!("expected a parse error, got {errors:?}"),
+    }
+
+    let errors = run_source("return 1;").unwrap_err();
+    assert_eq!(errors[0].column(), None);
+    assert_eq!(
+        errors[0].located(Some("main.lox")),
+        format!("main.lox:1: Error: {}", errors[0].message())
+    );
+}
+
+#[test]
+fn every_error_kind_has_an_exit_code() {
+    let errors = run_source("print 1 / \"a\";").unwrap_err();
+    assert_eq!(errors[0].exit_code(), 70);
+
+    let errors = run_source("print @;").unwrap_err();
+    assert_eq!(errors[0].exit_code(), 65);
+
+    let errors = run_source("print ;").unwrap_err();
+    assert_eq!(errors[0].exit_code(), 65);
+
+    let errors = run_source("return;").unwrap_err();
+    assert_eq!(errors[0].exit_code(), 65);
+}
+
+#[test]
+fn lists() {
+    assert_eq!(
+        run("var l = [1, 2, 3]; l[1] = 20; print l; print l[1]; print len(l);"),
+        "[1, 20, 3]\n20\n3\n"
+    );
+    assert!(matches!(
+        runtime_error("var l = [1]; print l[1];").error_type(),
+        RuntimeErrorType::IndexOutOfRange
+    ));
+    assert!(matches!(
+        runtime_error("var l = [1]; print l[0.5];").error_type(),
+        RuntimeErrorType::IndexMustBeInteger
+    ));
+    assert!(matches!(
+        runtime_error("print 1[0];").error_type(),
+        RuntimeErrorType::NotIndexable
+    ));
+}
+
+#[test]
+fn maps() {
+    let source = "
+        var m = {\"a\": 1};
+        m[\"b\"] = 2;
+        m[\"a\"] = 10;
+        print m[\"a\"];
+        print m[\"missing\"];
+        print m;
+    ";
+    assert_eq!(run(source), "10\nnil\n{a: 10, b: 2}\n");
+}
+
+#[test]
+fn conversions() {
+    assert_eq!(run("print str(42) + \"!\";"), "42!\n");
+    assert_eq!(run("print num(\"3.5\") + 1;"), "4.5\n");
+    assert!(matches!(
+        runtime_error("num(\"three\");").error_type(),
+        RuntimeErrorType::InvalidArgument(_)
+    ));
+}
+
+#[test]
+fn output_goes_to_the_configured_sink() {
+    assert_eq!(run("print \"hi\";"), "hi\n");
+}
+
+#[test]
+fn errors_go_to_the_configured_error_sink() {
+    let (output, error_output) = (Output::default(), Output::default());
+    let mut lox = Lox::with_output(Mode::Run, Box::new(output.clone()));
+    lox.set_error_output(Box::new(error_output.clone()));
+
+    assert!(lox.run("print 1;\nprint -nil;", Some("main.lox")).is_err());
+    assert!(lox.run("print 1 +;", Some("main.lox")).is_err());
+
+    assert_eq!(output.text(), "1\n");
+    assert_eq!(
+        error_output.text(),
+        "main.lox:2: Error: Operand must be a number.\n\
+         main.lox:1:10: Error: Expect expression.\n    print 1 +;\n             ^\n"
+    );
+}
+
+#[test]
+fn trace_logs_every_statement() {
+    log::set_logger(&TraceLogger).unwrap();
+    log::set_max_level(log::LevelFilter::Debug);
+
+    let output = Output::default();
+    let mut lox = Lox::with_output(Mode::Run, Box::new(output.clone()));
+    lox.set_trace(true);
+
+    lox.run("var a = 1 + 2;\nprint a;\na * 2;", None).unwrap();
+
+    let trace = TRACE.lock().unwrap().clone();
+    assert!(
+        trace.contains(&String::from("[line 1] (var a (+ 1 2))")),
+        "{trace:?}"
+    );
+    assert!(
+        trace.contains(&String::from("[line 2] (print a)")),
+        "{trace:?}"
+    );
+    assert!(trace.contains(&String::from("=> 6")), "{trace:?}");
+    assert_eq!(output.text(), "3\n");
+}
+
+#[test]
+fn unbounded_recursion_is_a_runtime_error() {
+    assert!(matches!(
+        runtime_error("fun f() { return f(); } f();").error_type(),
+        RuntimeErrorType::StackOverflow
+    ));
+}
+
+#[test]
+fn recursion_up_to_the_default_limit_runs_in_any_build() {
+    let source = "
+        fun down(n) {
+            if (n > 0) {
+                while (true) {
+                    { for (var i = 0; i < 1; i = i + 1) { return 1 + down(n - 1); } }
+                }
+            }
+            return 0;
+        }
+        print down(990);
+    ";
+
+    assert_eq!(run(source), "990\n");
+}
+
+#[test]
+fn assertions() {
+    assert_eq!(run("assert 1 < 2; assert true, \"never shown\";"), "");
+    assert!(matches!(
+        runtime_error("assert 1 > 2;").error_type(),
+        RuntimeErrorType::AssertionFailed(None)
+    ));
+    assert!(matches!(
+        runtime_error("assert false, \"broken\";").error_type(),
+        RuntimeErrorType::AssertionFailed(Some(message)) if message == "broken"
+    ));
+}
+
+#[test]
+fn bitwise_operators() {
+    assert_eq!(
+        run("print 6 & 3; print 6 | 3; print 6 ^ 3; print 1 << 4; print 32 >> 2;"),
+        "2\n7\n5\n16\n8\n"
+    );
+    assert!(matches!(
+        runtime_error("print 1.5 & 1;").error_type(),
+        RuntimeErrorType::OperandsMustBeIntegers
+    ));
+    assert!(matches!(
+        runtime_error("print 1 << 64;").error_type(),
+        RuntimeErrorType::InvalidShift
+    ));
+}
+
+#[test]
+fn switch_statement() {
+    let source = "
+        fun describe(n) {
+            switch (n) {
+                case 1: print \"one\";
+                case 2: print \"two\";
+                default: print \"many\";
+            }
+        }
+        describe(2);
+        describe(5);
+    ";
+    assert_eq!(run(source), "two\nmany\n");
+    assert_eq!(run("switch (3) { case 1: print 1; }"), "");
+}
+
+#[test]
+fn default_parameters() {
+    let source = "
+        fun greet(name, greeting = \"hello\") { print greeting + \" \" + name; }
+        greet(\"a\");
+        greet(\"b\", \"bye\");
+    ";
+    assert_eq!(run(source), "hello a\nbye b\n");
+
+    assert!(matches!(
+        runtime_error("fun f(a, b = 1) {} f();").error_type(),
+        RuntimeErrorType::ArityRangeMismatch {
+            min: 1,
+            max: 2,
+            got: 0
+        }
+    ));
+}
+
+#[test]
+fn rest_parameters() {
+    let source = "
+        fun sum(...numbers) {
+            var total = 0;
+            for (n in numbers) total = total + n;
+            return total;
+        }
+        fun rest(first, ...others) { return others; }
+        print sum(1, 2, 3);
+        print rest(1);
+    ";
+    assert_eq!(run(source), "6\n[]\n");
+
+    assert!(matches!(
+        runtime_error("fun f(a, ...rest) {} f();").error_type(),
+        RuntimeErrorType::TooFewArguments { min: 1, got: 0 }
+    ));
+}
+
+#[test]
+fn for_in_loops() {
+    assert_eq!(
+        run("var sum = 0; for (n in [1, 2, 3]) sum = sum + n; print sum;"),
+        "6\n"
+    );
+    assert_eq!(run("for (c in \"ab\") print c;"), "a\nb\n");
+    assert!(matches!(
+        runtime_error("for (x in 1) print x;").error_type(),
+        RuntimeErrorType::NotIterable
+    ));
+}
+
+#[test]
+fn getter_runs_when_the_property_is_read() {
+    let source = "
+        class Circle {
+            init(r) { this.r = r; }
+            diameter { return this.r * 2; }
+        }
+        print Circle(3).diameter;
+    ";
+
+    assert_eq!(run(source), "6\n");
+}
+
+#[test]
+fn calling_a_getter_is_a_runtime_error() {
+    let source = "
+        class Circle {
+            area { return 3; }
+            callback { return fun () { return 1; }; }
+        }
+    ";
+
+    for call in ["Circle().area();", "Circle().callback();"] {
+        let error = runtime_error(&format!("{source} {call}"));
+
+        assert!(matches!(
+            error.error_type(),
+            RuntimeErrorType::GetterCalled(_)
+        ));
+    }
+}
+
+#[test]
+fn static_methods() {
+    assert_eq!(
+        run("class Math { static square(x) { return x * x; } } print Math.square(3);"),
+        "9\n"
+    );
+    assert!(matches!(
+        resolve_error("class C { static f() { return this; } }").error_type(),
+        ResolveErrorType::ThisInStaticMethod
+    ));
+}
+
+#[test]
+fn exceptions() {
+    assert_eq!(
+        run("try { throw \"oops\"; print \"skipped\"; } catch (e) { print e; }"),
+        "oops\n"
+    );
+
+    let source = "
+        try {
+            try { throw 1; } catch (e) { throw e + 1; }
+        } catch (e) {
+            print e;
+        }
+    ";
+    assert_eq!(run(source), "2\n");
+
+    let source = "
+        fun fail() { throw \"deep\"; }
+        try { fail(); } catch (e) { print \"caught \" + e; }
+    ";
+    assert_eq!(run(source), "caught deep\n");
+
+    assert!(matches!(
+        runtime_error("throw \"uncaught\";").error_type(),
+        RuntimeErrorType::Thrown(_)
+    ));
+}
+
+#[test]
+fn inherited_methods_through_a_deep_hierarchy() {
+    let mut source = String::from("class C0 { base() { return 1; } name() { return 0; } }\n");
+    for depth in 1..50 {
+        source.push_str(&format!("class C{depth} < C{} {{}}\n", depth - 1));
+    }
+    source.push_str("class Last < C49 { name() { return 50; } }\n");
+    source.push_str(
+        "var last = Last(); var sum = 0;
+        for (var i = 0; i < 1000; i = i + 1) sum = sum + last.base();
+        print sum; print last.name();",
+    );
+
+    assert_eq!(run(&source), "1000\n50\n");
+}
+
+#[test]
+fn type_of_values() {
+    let source = "
+        class C {}
+        print typeof 1;
+        print typeof 1.5;
+        print typeof \"s\";
+        print typeof true;
+        print typeof nil;
+        print typeof clock;
+        print typeof C;
+        print typeof C();
+        print typeof [];
+        print typeof {};
+    ";
+
+    assert_eq!(
+        run(source),
+        "number\nnumber\nstring\nboolean\nnil\nfunction\nclass\ninstance\nlist\nmap\n"
+    );
+}
+
+#[test]
+fn integers_and_floats() {
+    assert_eq!(run("print 7 / 2;"), "3.5\n");
+    assert_eq!(run("print 7 ~/ 2;"), "3\n");
+    assert_eq!(run("print 1 + 0.5;"), "1.5\n");
+    assert_eq!(run("print 2 * 3;"), "6\n");
+    assert_eq!(run("print 9223372036854775807 + 1 > 0;"), "true\n");
+    assert!(matches!(
+        runtime_error("print 1 ~/ 0;").error_type(),
+        RuntimeErrorType::DivisionByZero
+    ));
+}
+
+#[test]
+fn factorial() {
+    assert_eq!(run("print 5!;"), "120\n");
+    assert_eq!(run("print 0!;"), "1\n");
+    assert!(matches!(
+        runtime_error("print (-1)!;").error_type(),
+        RuntimeErrorType::OperandMustBeNonNegativeInteger
+    ));
+}
+
+#[test]
+fn number_precision() {
+    let output = Output::default();
+    let mut lox = Lox::with_output(Mode::Run, Box::new(output.clone()));
+    lox.set_precision(Some(2));
+
+    lox.run("print 1 / 3; print 2; print [0.5];", None).unwrap();
+
+    assert_eq!(output.text(), "0.33\n2\n[0.50]\n");
+    assert_eq!(run("print 1 / 3;"), "0.3333333333333333\n");
+}
+
+#[test]
+fn printing_values() {
+    let source = "
+        fun named() {}
+        class Box {}
+        print 1;
+        print 2.5;
+        print 3.0;
+        print \"text\";
+        print true;
+        print nil;
+        print named;
+        print clock;
+        print Box;
+        print Box();
+        print [1, \"a\", [nil]];
+        print {\"b\": 2, \"a\": 1};
+    ";
+
+    assert_eq!(
+        run(source),
+        "1\n2.5\n3\ntext\ntrue\nnil\n<fn named>\n<native fn>\n<class Box>\n<instance Box>\n[1, a, [nil]]\n{a: 1, b: 2}\n"
+    );
+}
+
+#[test]
+fn arity_of_callables() {
+    assert_eq!(
+        run("fun two(a, b) {} class C { init(a) {} } print arity(two); print arity(clock); print arity(C);"),
+        "2\n0\n1\n"
+    );
+    assert!(matches!(
+        runtime_error("arity(1);").error_type(),
+        RuntimeErrorType::InvalidArgument(_)
+    ));
+}
+
+#[test]
+fn map_keys() {
+    assert_eq!(
+        run("var m = {1: \"int\"}; m[1.0] = \"float\"; print m;"),
+        "{1: float}\n"
+    );
+    assert_eq!(
+        run("var nan = num(\"NaN\"); var m = {}; m[nan] = 1; m[nan] = 2; print len(m);"),
+        "1\n"
+    );
+    assert!(matches!(
+        runtime_error("var m = {}; m[[1]] = 1;").error_type(),
+        RuntimeErrorType::InvalidMapKey
+    ));
+}
+
+#[test]
+fn not_a_number() {
+    assert_eq!(
+        run("var nan = num(\"NaN\"); print nan == nan; print is_nan(nan); print nan < 1; print nan > 1;"),
+        "false\ntrue\nfalse\nfalse\n"
+    );
+}
+
+#[test]
+fn do_while_runs_the_body_first() {
+    assert_eq!(run("do print 1; while (false);"), "1\n");
+    assert_eq!(
+        run("var i = 0; do { print i; i = i + 1; } while (i < 3);"),
+        "0\n1\n2\n"
+    );
+}
@@ -0,0 +1,408 @@
+use std::time::Instant;
+
+use rlox::lexer::{
+    scan_tokens, tokens_json, Lexer, LexingError, LexingErrorType, Span, Token, TokenType,
+};
+
+/// Scans a source that is expected to be valid, without the final `Eof`.
+fn token_types(source: &str) -> Vec<TokenType> {
+    let (mut tokens, errors) = scan_tokens(source);
+    assert!(errors.is_empty(), "{source:?} failed to scan: {errors:?}");
+
+    assert!(matches!(
+        tokens.pop().map(|token| token.token_type().clone()),
+        Some(TokenType::Eof)
+    ));
+    tokens
+        .iter()
+        .map(|token| token.token_type().clone())
+        .collect()
+}
+
+/// Scans a source with exactly one lexing error in it.
+fn lexing_error(source: &str) -> LexingError {
+    let (tokens, mut errors) = scan_tokens(source);
+    assert!(matches!(
+        tokens.last().map(Token::token_type),
+        Some(TokenType::Eof)
+    ));
+
+    match errors.pop() {
+        Some(error) if errors.is_empty() => error,
+        error => panic!("{source:?} should have one error, got {error:?}"),
+    }
+}
+
+#[test]
+fn lexes_a_large_file() {
+    let source = "var x = 1; // comment\n".repeat(100_000);
+    let (tokens, errors) = scan_tokens(&source);
+
+    assert!(errors.is_empty());
+    assert_eq!(tokens.len(), 500_001);
+    assert_eq!(tokens[tokens.len() - 2].line(), 100_000);
+}
+
+#[test]
+fn line_comments_are_skipped() {
+    use TokenType::*;
+
+    assert_eq!(
+        token_types("var x = 1; // hello\nvar y = 2;"),
+        [
+            Var,
+            Identifier("x".into()),
+            Equal,
+            Int(1),
+            Semicolon,
+            Var,
+            Identifier("y".into()),
+            Equal,
+            Int(2),
+            Semicolon,
+        ]
+    );
+    assert!(token_types("// only a comment").is_empty());
+}
+
+#[test]
+fn tokens_end_with_eof_even_after_errors() {
+    let (tokens, errors) = scan_tokens("print 1;");
+    assert!(errors.is_empty());
+    assert_eq!(tokens.len(), 4);
+    assert!(matches!(tokens[3].token_type(), TokenType::Eof));
+
+    let (tokens, errors) = scan_tokens("print 1 @ 2;");
+    assert_eq!(errors.len(), 1);
+    assert_eq!(tokens.len(), 5);
+    assert!(matches!(tokens[4].token_type(), TokenType::Eof));
+}
+
+#[test]
+fn tokens_know_their_column() {
+    let (tokens, _) = scan_tokens("var a;\n  print a;");
+    let positions = tokens
+        .iter()
+        .map(|token| (token.line(), token.column()))
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        positions,
+        [(1, 1), (1, 5), (1, 6), (2, 3), (2, 9), (2, 10), (2, 11)]
+    );
+    assert_eq!(tokens[3].to_string(), "2:3 Print");
+}
+
+#[test]
+fn block_comments() {
+    assert_eq!(
+        token_types("1 /* one */ 2"),
+        [TokenType::Int(1), TokenType::Int(2)]
+    );
+    assert_eq!(
+        token_types("1 /* outer /* inner */ still outer */ 2"),
+        [TokenType::Int(1), TokenType::Int(2)]
+    );
+    assert!(matches!(
+        lexing_error("1 /* never closed").error_type(),
+        LexingErrorType::UnterminatedBlockComment
+    ));
+}
+
+#[test]
+fn string_escapes() {
+    assert_eq!(
+        token_types(r#""a\nb\t\"c\"\\""#),
+        [TokenType::String("a\nb\t\"c\"\\".into())]
+    );
+    assert!(matches!(
+        lexing_error(r#""\q""#).error_type(),
+        LexingErrorType::InvalidEscapeSequence
+    ));
+}
+
+#[test]
+fn hexadecimal_and_binary_literals() {
+    assert_eq!(token_types("0xff"), [TokenType::Int(255)]);
+    assert_eq!(
+        token_types("0b0 0b101"),
+        [TokenType::Int(0), TokenType::Int(5)]
+    );
+    for source in ["0x", "0b", "0b12"] {
+        assert!(matches!(
+            lexing_error(source).error_type(),
+            LexingErrorType::NumberParsingError
+        ));
+    }
+}
+
+#[test]
+fn digit_separators_and_exponents() {
+    assert_eq!(token_types("1_234"), [TokenType::Int(1234)]);
+    assert_eq!(token_types("2.718_5"), [TokenType::Number(2.7185)]);
+    assert_eq!(token_types("1e3"), [TokenType::Number(1000.0)]);
+    assert_eq!(token_types("2.5E-1"), [TokenType::Number(0.25)]);
+    for source in ["1__0", "1_", "1e_3"] {
+        assert!(
+            !scan_tokens(source).1.is_empty(),
+            "{source:?} should be an error"
+        );
+    }
+}
+
+#[test]
+fn iterator_yields_what_scan_tokens_returns() {
+    let source = "fun f(a) { return a + 1; } print f(2);";
+    let tokens = Lexer::new(source)
+        .map(|token| token.unwrap())
+        .collect::<Vec<_>>();
+
+    assert_eq!(tokens, scan_tokens(source).0);
+}
+
+#[test]
+fn spans_reconstruct_the_lexemes() {
+    let source = "var name = \"rlox\" + 12.5;";
+    let (tokens, _) = scan_tokens(source);
+
+    for token in &tokens {
+        assert_eq!(token.span().slice(source), token.lexeme());
+    }
+    assert_eq!(tokens[3].span().slice(source), "\"rlox\"");
+}
+
+#[test]
+fn equality_of_token_types() {
+    assert_eq!(TokenType::Number(1.0), TokenType::Number(1.0));
+    assert_ne!(TokenType::Number(1.0), TokenType::Number(2.0));
+    assert!(TokenType::Number(1.0).same_kind(&TokenType::Number(2.0)));
+    assert!(TokenType::String("a".into()).same_kind(&TokenType::String("b".into())));
+    assert!(TokenType::Plus.same_kind(&TokenType::Plus));
+    assert!(!TokenType::Plus.same_kind(&TokenType::Minus));
+    assert!(!TokenType::Int(1).same_kind(&TokenType::Number(1.0)));
+}
+
+#[test]
+fn unterminated_interpolation() {
+    assert!(matches!(
+        lexing_error("print \"a ${1 + 2").error_type(),
+        LexingErrorType::UnterminatedInterpolation
+    ));
+}
+
+#[test]
+fn tokens_as_json() {
+    let (tokens, _) = scan_tokens("var x = 1;");
+
+    assert_eq!(
+        tokens_json(&tokens),
+        r#"[
+  {"type": "Var", "lexeme": "var", "line": 1, "column": 1, "start": 0, "end": 3},
+  {"type": "Identifier", "lexeme": "x", "line": 1, "column": 5, "start": 4, "end": 5},
+  {"type": "Equal", "lexeme": "=", "line": 1, "column": 7, "start": 6, "end": 7},
+  {"type": "Int", "lexeme": "1", "line": 1, "column": 9, "start": 8, "end": 9},
+  {"type": "Semicolon", "lexeme": ";", "line": 1, "column": 10, "start": 9, "end": 10},
+  {"type": "Eof", "lexeme": "", "line": 1, "column": 11, "start": 10, "end": 10}
+]"#
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn tokens_round_trip_through_serde() {
+    use rlox::lexer::Lexer;
+
+    let json = Lexer::new("var x = 1;").tokens_json().unwrap();
+    let tokens: Vec<rlox::lexer::Token> = serde_json::from_str(&json).unwrap();
+
+    assert_eq!(tokens, scan_tokens("var x = 1;").0);
+    assert!(matches!(
+        Lexer::new("var @;").tokens_json().unwrap_err().as_slice(),
+        [error] if matches!(error.error_type(), LexingErrorType::UnexpectedCharacter)
+    ));
+}
+
+#[test]
+fn unicode_identifiers() {
+    assert_eq!(token_types("café"), [TokenType::Identifier("café".into())]);
+    assert_eq!(
+        token_types("переменная"),
+        [TokenType::Identifier("переменная".into())]
+    );
+}
+
+#[test]
+fn unterminated_string_points_at_its_start() {
+    let (_, errors) = scan_tokens("print 1;\n\"first\nsecond\nthird");
+
+    assert!(matches!(
+        errors.as_slice(),
+        [error] if matches!(error.error_type(), LexingErrorType::UnterminatedString) && error.line() == 2
+    ));
+}
+
+#[test]
+fn one_and_two_character_operators() {
+    use TokenType::*;
+
+    assert_eq!(
+        token_types("! != = == < <= > >="),
+        [
+            Bang,
+            BangEqual,
+            Equal,
+            EqualEqual,
+            Less,
+            LessEqual,
+            Greater,
+            GreaterEqual
+        ]
+    );
+}
+
+#[test]
+fn spelling_of_token_types() {
+    assert_eq!(TokenType::While.lexeme(), Some("while"));
+    assert_eq!(TokenType::LessEqual.lexeme(), Some("<="));
+    assert_eq!(TokenType::LeftBrace.lexeme(), Some("{"));
+    assert_eq!(TokenType::DotDotDot.lexeme(), Some("..."));
+    assert_eq!(TokenType::Number(1.0).lexeme(), None);
+}
+
+#[test]
+fn error_reports_its_column() {
+    let (_, errors) = scan_tokens("var @ = 1;");
+
+    assert!(matches!(errors.as_slice(), [error] if error.column() == 5));
+}
+
+#[test]
+fn nul_inside_a_string_is_kept() {
+    assert_eq!(token_types("\"a\0b\""), [TokenType::String("a\0b".into())]);
+}
+
+#[test]
+fn line_directive_remaps_the_following_errors() {
+    let (_, errors) = scan_tokens("print 1;\n//#line 40 \"gen.lox\"\nprint 2;\n@");
+
+    match errors.as_slice() {
+        [error] => {
+            assert!(matches!(
+                error.error_type(),
+                LexingErrorType::UnexpectedCharacter
+            ));
+            assert_eq!(error.line(), 41);
+            assert_eq!(error.file(), Some("gen.lox"));
+        }
+        errors => panic!("expected one error, got {errors:?}"),
+    }
+}
+
+#[test]
+fn comments_that_only_start_like_a_directive_are_comments() {
+    let (tokens, errors) = scan_tokens("//#lines of code\n//#linear\nprint 1;");
+
+    assert!(errors.is_empty(), "{errors:?}");
+    assert!(matches!(tokens[0].token_type(), TokenType::Print));
+    assert_eq!(tokens[0].line(), 3);
+}
+
+#[test]
+fn malformed_directive_is_an_error() {
+    let (_, errors) = scan_tokens("//#line forty\n");
+
+    assert!(matches!(
+        errors.as_slice(),
+        [error] if matches!(error.error_type(), LexingErrorType::InvalidLineDirective)
+    ));
+}
+
+#[test]
+fn token_accessors() {
+    let span = Span {
+        start: 4,
+        end: 9,
+        line: 2,
+    };
+    let token = Token::new(TokenType::Identifier("count".into()), span, 3);
+
+    assert_eq!(token.token_type(), &TokenType::Identifier("count".into()));
+    assert_eq!(token.lexeme(), "count");
+    assert_eq!(token.name(), "count");
+    assert_eq!(token.line(), 2);
+    assert_eq!(token.column(), 3);
+    assert_eq!(token.span(), &span);
+    assert_eq!(token.file(), None);
+    assert_eq!(Token::new(TokenType::Plus, span, 3).lexeme(), "+");
+}
+
+#[test]
+fn pathological_numbers_are_errors() {
+    for source in ["1e", "1e+", "2.5e-", "0xfffffffffffffffffffff"] {
+        assert!(matches!(
+            lexing_error(source).error_type(),
+            LexingErrorType::NumberParsingError
+        ));
+    }
+
+    // too large for an integer, but still a number rather than an error
+    assert_eq!(
+        token_types("99999999999999999999"),
+        [TokenType::Number(1e20)]
+    );
+}
+
+#[test]
+fn keywords_and_identifiers() {
+    use TokenType::*;
+
+    assert_eq!(
+        token_types(
+            "and class else false for fun if nil or print return super this true var while"
+        ),
+        [
+            And, Class, Else, False, For, Fun, If, Nil, Or, Print, Return, Super, This, True, Var,
+            While
+        ]
+    );
+    assert_eq!(
+        token_types(
+            "break continue assert switch case default in static try catch throw typeof include do"
+        ),
+        [
+            Break, Continue, Assert, Switch, Case, Default, In, Static, Try, Catch, Throw, TypeOf,
+            Include, Do
+        ]
+    );
+}
+
+#[test]
+fn words_that_only_resemble_keywords_are_identifiers() {
+    for name in [
+        "classy", "orchid", "_if", "If", "NIL", "fu", "whiles", "do2",
+    ] {
+        assert_eq!(
+            token_types(name),
+            [TokenType::Identifier(name.into())],
+            "{name}"
+        );
+    }
+}
+
+/// Timing rather than a check, run it with
+/// `cargo test --release --test lexer -- --ignored --nocapture`.
+#[test]
+#[ignore]
+fn lexes_identifiers_quickly() {
+    let source = (0..200_000)
+        .map(|i| format!("var name{i} = fun_{i} and classy or nil;\n"))
+        .collect::<String>();
+
+    let start = Instant::now();
+    let (tokens, errors) = scan_tokens(&source);
+    let elapsed = start.elapsed();
+
+    assert!(errors.is_empty());
+    assert_eq!(tokens.len(), 200_000 * 9 + 1);
+    eprintln!("{} tokens in {elapsed:?}", tokens.len());
+}
@@ -0,0 +1,265 @@
+use rlox::{
+    ast_dot, ast_printer, fold,
+    lexer::scan_tokens,
+    parser::{ParseError, ParseErrorType, Parser, Stmt},
+};
+
+fn parse(source: &str) -> Vec<Stmt> {
+    let (tokens, errors) = scan_tokens(source);
+    assert!(errors.is_empty(), "{source:?} failed to scan: {errors:?}");
+
+    match Parser::new(tokens).parse_program() {
+        Ok(statements) => statements,
+        Err(errors) => panic!("{source:?} failed to parse: {errors:?}"),
+    }
+}
+
+/// Parses the program and prints every statement as an s-expression.
+fn ast(source: &str) -> Vec<String> {
+    parse(source).iter().map(ast_printer::print_stmt).collect()
+}
+
+fn parse_errors(source: &str) -> Vec<ParseError> {
+    let (tokens, errors) = scan_tokens(source);
+    assert!(errors.is_empty(), "{source:?} failed to scan: {errors:?}");
+
+    Parser::new(tokens).parse_program().unwrap_err()
+}
+
+/// Error of a program with exactly one parse error in it.
+fn parse_error(source: &str) -> ParseError {
+    let mut errors = parse_errors(source);
+
+    match errors.pop() {
+        Some(error) if errors.is_empty() => error,
+        error => panic!("{source:?} should have one error, got {error:?}"),
+    }
+}
+
+#[test]
+fn multiplication_binds_tighter_than_addition() {
+    assert_eq!(ast("1 + 2 * 3;"), ["(+ 1 (* 2 3))"]);
+    assert_eq!(ast("1 * 2 + 3;"), ["(+ (* 1 2) 3)"]);
+    assert_eq!(ast("1 - 2 - 3;"), ["(- (- 1 2) 3)"]);
+}
+
+#[test]
+fn expressions_as_s_expressions() {
+    assert_eq!(ast("-123 * (45.67);"), ["(* (- 123) (group 45.67))"]);
+    assert_eq!(ast("!true == false;"), ["(== (! true) false)"]);
+    assert_eq!(ast("a = b = nil;"), ["(= a (= b nil))"]);
+}
+
+#[test]
+fn statements_in_order() {
+    let statements = parse("var a = 1; print a; { a; }");
+
+    assert!(matches!(
+        statements.as_slice(),
+        [Stmt::Var { .. }, Stmt::Print(_), Stmt::Block(_)]
+    ));
+}
+
+#[test]
+fn missing_semicolon() {
+    let error = parse_error("print 1\nprint 2;");
+
+    assert!(matches!(
+        error.error_type(),
+        ParseErrorType::ExpectedSemicolon
+    ));
+    assert_eq!((error.line(), error.column()), (2, 1));
+}
+
+#[test]
+fn every_statement_error_is_reported() {
+    assert_eq!(parse_errors("print ; var = 1; print 3;").len(), 2);
+}
+
+#[test]
+fn variable_declarations_and_blocks() {
+    assert_eq!(
+        ast("var a; var b = 1; { print b; }"),
+        ["(var a)", "(var b 1)", "(block (print b))"]
+    );
+}
+
+#[test]
+fn else_binds_to_the_nearest_if() {
+    assert_eq!(
+        ast("if (a) print 1; else print 2;"),
+        ["(if a (print 1) (print 2))"]
+    );
+    assert_eq!(
+        ast("if (a) if (b) print 1; else print 2;"),
+        ["(if a (if b (print 1) (print 2)))"]
+    );
+}
+
+#[test]
+fn while_loops_and_logical_operators() {
+    assert_eq!(ast("while (a) a = a - 1;"), ["(while a (= a (- a 1)))"]);
+    assert_eq!(ast("x or y and z;"), ["(or x (and y z))"]);
+}
+
+#[test]
+fn for_loop_desugars_to_while() {
+    assert_eq!(
+        ast("for (var i = 0; i < 3; i = i + 1) print i;"),
+        ["(block (var i 0) (while (< i 3) (print i) (= i (+ i 1))))"]
+    );
+    assert_eq!(ast("for (;;) print 1;"), ["(while true (print 1))"]);
+}
+
+#[test]
+fn function_declarations_and_calls() {
+    assert_eq!(
+        ast("fun add(a, b) { return a + b; }"),
+        ["(fun add (a b) (return (+ a b)))"]
+    );
+    assert_eq!(ast("f(1, 2)(3);"), ["(call (call f 1 2) 3)"]);
+}
+
+#[test]
+fn property_access() {
+    assert_eq!(ast("a.b.c = 1;"), ["(=. c (. b a) 1)"]);
+    assert_eq!(ast("a.b().c;"), ["(. c (call (. b a)))"]);
+}
+
+#[test]
+fn superclass_and_super_calls() {
+    assert_eq!(
+        ast("class B < A { init() { super.init(); } }"),
+        ["(class B < A (fun init () (call super.init)))"]
+    );
+}
+
+#[test]
+fn conditional_binds_rightward() {
+    assert_eq!(ast("a ? 1 : b ? 2 : 3;"), ["(?: a 1 (?: b 2 3))"]);
+    assert_eq!(ast("x = a ? 1 : 2;"), ["(= x (?: a 1 2))"]);
+}
+
+#[test]
+fn compound_assignment_targets() {
+    assert_eq!(ast("x += 1;"), ["(= x (+ x 1))"]);
+    assert_eq!(ast("p.x -= 1;"), ["(-=. x p 1)"]);
+    assert_eq!(ast("l[0] *= 2;"), ["([]*= l 0 2)"]);
+}
+
+#[test]
+fn compound_assignment_to_a_literal_is_rejected() {
+    let errors = parse_errors("1 += 2;");
+
+    assert!(matches!(
+        errors.as_slice(),
+        [error] if matches!(error.error_type(), ParseErrorType::InvalidAssignmentTarget)
+    ));
+}
+
+#[test]
+fn increment_targets() {
+    assert_eq!(ast("++x;"), ["(= x (+ x 1))"]);
+    assert!(matches!(
+        parse_error("++1;").error_type(),
+        ParseErrorType::InvalidIncrementTarget
+    ));
+}
+
+#[test]
+fn lambda_expressions() {
+    assert_eq!(
+        ast("var f = fun (x) { return x; };"),
+        ["(var f (fun (x) (return x)))"]
+    );
+}
+
+#[test]
+fn parse_errors_keep_the_file_of_their_token() {
+    let error = parse_error("//#line 7 \"gen.lox\"\nprint ;");
+
+    assert_eq!(error.file(), Some("gen.lox"));
+    assert_eq!(error.line(), 7);
+}
+
+#[test]
+fn list_literals_and_indexing() {
+    assert_eq!(ast("[1, 2][0];"), ["([] (list 1 2) 0)"]);
+    assert_eq!(ast("l[0] = [];"), ["([]= l 0 (list))"]);
+}
+
+#[test]
+fn folding_happens_after_parsing() {
+    let mut statements = parse("print (1 + 2) * 3;");
+    assert_eq!(
+        ast_printer::print_stmt(&statements[0]),
+        "(print (* (group (+ 1 2)) 3))"
+    );
+
+    fold::fold_program(&mut statements);
+    assert_eq!(ast_printer::print_stmt(&statements[0]), "(print 9)");
+}
+
+#[test]
+fn division_by_zero_is_not_folded() {
+    let mut statements = parse("print 1 / 0; print 1.5 / 0.0; print x + 1 * 2;");
+    fold::fold_program(&mut statements);
+
+    let folded = statements
+        .iter()
+        .map(ast_printer::print_stmt)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        folded,
+        ["(print (/ 1 0))", "(print (/ 1.5 0))", "(print (+ x 2))"]
+    );
+}
+
+#[test]
+fn default_parameters() {
+    assert_eq!(ast("fun f(a, b = 2) {}"), ["(fun f (a (= b 2)))"]);
+    assert!(matches!(
+        parse_error("fun f(a = 1, b) {}").error_type(),
+        ParseErrorType::RequiredParameterAfterDefault
+    ));
+}
+
+#[test]
+fn rest_parameters() {
+    assert_eq!(ast("fun f(a, ...rest) {}"), ["(fun f (a ...rest))"]);
+    assert!(matches!(
+        parse_error("fun f(...rest, a) {}").error_type(),
+        ParseErrorType::RestParameterNotLast
+    ));
+}
+
+#[test]
+fn getters() {
+    assert_eq!(
+        ast("class Circle { area { return 3; } }"),
+        ["(class Circle (get area (return 3)))"]
+    );
+}
+
+#[test]
+fn dot_output() {
+    let dot = ast_dot::print_program(&parse("print 1 + 2;"));
+
+    assert!(dot.starts_with("digraph ast {\n"), "{dot}");
+    assert!(dot.contains("n0 [label=\"program\"];"), "{dot}");
+    assert!(dot.contains("n1 [label=\"print\"];"), "{dot}");
+    assert!(dot.contains("n2 [label=\"+\"];"), "{dot}");
+    assert!(dot.contains("n0 -> n1 [label=\"statement\"];"), "{dot}");
+    assert!(dot.contains("n1 -> n2 [label=\"value\"];"), "{dot}");
+    assert!(dot.contains("n2 -> n3 [label=\"left\"];"), "{dot}");
+    assert!(dot.ends_with('}'), "{dot}");
+}
+
+#[test]
+fn chained_comparisons_are_rejected() {
+    assert!(matches!(
+        parse_error("1 < 2 < 3;").error_type(),
+        ParseErrorType::ChainedComparison
+    ));
+    assert_eq!(ast("1 < 2 and 2 < 3;"), ["(and (< 1 2) (< 2 3))"]);
+}
@@ -0,0 +1,148 @@
+use std::{
+    env, fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use rlox::lox::{Lox, Mode};
+
+/// Buffer that stays readable after the `Lox` writing to it took a handle.
+#[derive(Clone, Default)]
+struct Output(Arc<Mutex<Vec<u8>>>);
+
+impl Output {
+    fn text(&self) -> String {
+        String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Directory of its own for the files of a test, so that tests running in
+/// parallel don't overwrite each other's files.
+fn scratch(test: &str) -> PathBuf {
+    let directory = env::temp_dir().join(format!("rlox-promt-{}-{test}", std::process::id()));
+    fs::create_dir_all(&directory).unwrap();
+
+    directory
+}
+
+/// Runs the promt on `input` and returns what it printed and what it
+/// reported, the history is kept in `history` if given.
+fn promt(input: &str, history: Option<&Path>) -> (String, String) {
+    let (output, error_output) = (Output::default(), Output::default());
+    let mut lox = Lox::with_output(Mode::Run, Box::new(output.clone()));
+    lox.set_error_output(Box::new(error_output.clone()));
+    lox.set_history(history.map(Path::to_owned));
+
+    lox.run_promt(input.as_bytes());
+
+    (output.text(), error_output.text())
+}
+
+fn output(input: &str) -> String {
+    promt(input, None).0
+}
+
+#[test]
+fn promt_ends_at_the_end_of_the_input_or_on_exit() {
+    assert_eq!(output("print 1;\n"), "> 1\n> ");
+    assert_eq!(output("print 1;"), "> 1\n> ");
+    assert_eq!(output("print 1;\n.exit\nprint 2;\n"), "> 1\n> ");
+}
+
+#[test]
+fn promt_keeps_going_after_an_error() {
+    let (output, errors) = promt("print nope;\nprint 1;\n", None);
+
+    assert_eq!(output, "> > 1\n> ");
+    assert_eq!(errors, "<repl>:1: Error: Undefined variable 'nope'.\n");
+}
+
+#[test]
+fn promt_runs_any_number_of_lines() {
+    let input = "print 1;\n".repeat(10_000);
+
+    assert_eq!(output(&input).matches("1\n").count(), 10_000);
+}
+
+#[test]
+fn promt_lists_its_history() {
+    let path = scratch("history").join("history");
+    let _ = fs::remove_file(&path);
+
+    promt("var a = 1;\n\nprint a;\n", Some(&path));
+    let (output, _) = promt("1 + 1\n.history\n", Some(&path));
+
+    assert_eq!(
+        output,
+        "> 2\n>    1  var a = 1;\n   2  print a;\n   3  1 + 1\n> "
+    );
+    assert_eq!(
+        fs::read_to_string(&path).unwrap(),
+        "var a = 1;\nprint a;\n1 + 1\n"
+    );
+}
+
+#[test]
+fn promt_without_a_history_file_remembers_the_session() {
+    assert_eq!(output("print 1;\n.history\n"), "> 1\n>    1  print 1;\n> ");
+}
+
+#[test]
+fn promt_prints_the_values_of_expressions() {
+    assert_eq!(
+        output("1 + 2\nvar x = 5;\nx * 2\nprint x;\n"),
+        "> 3\n> > 10\n> 5\n> "
+    );
+    assert_eq!(output("\"text\"\nnil\n"), "> text\n> nil\n> ");
+}
+
+#[test]
+fn promt_lists_the_globals() {
+    let globals = output("var a = 1;\nvar b = \"two\";\n.env\n");
+
+    assert!(globals.contains("a = 1\n"), "{globals}");
+    assert!(globals.contains("b = two\n"), "{globals}");
+    assert!(globals.contains("clock = <native fn>\n"), "{globals}");
+}
+
+#[test]
+fn promt_lines_share_their_definitions() {
+    let source = "
+        var count = 0;
+        fun bump() { count = count + 1; return count; }
+        class Counter { init() { this.n = bump(); } }
+        bump();
+        Counter().n
+    ";
+    let input = source.lines().map(str::trim).collect::<Vec<_>>().join("\n");
+
+    assert!(output(&input).ends_with("> 2\n> "), "{}", output(&input));
+}
+
+#[test]
+fn promt_loads_definitions_from_a_file() {
+    let path = scratch("load").join("util.lox");
+    fs::write(&path, "fun twice(x) { return x * 2; }\n").unwrap();
+
+    assert_eq!(
+        output(&format!(".load {}\ntwice(4)\n.load\n", path.display())),
+        "> > 8\n> Usage: .load <file>\n> "
+    );
+
+    let (_, errors) = promt(".load nowhere.lox\n", None);
+    assert!(
+        errors.starts_with("Error: could not read 'nowhere.lox': "),
+        "{errors}"
+    );
+}